@@ -9,6 +9,7 @@ fn main() {
   println!("cargo:rustc-link-lib=wiretap");
   println!("cargo:rustc-link-lib=wsutil");
   println!("cargo:rustc-link-lib=glib-2.0");
+  println!("cargo:rustc-link-lib=pcap");
 
   // Tell cargo to look for shared libraries in the specified directory
   // shared library.
@@ -0,0 +1,41 @@
+#!/bin/sh
+# A minimal extcap for the extcap tests: one interface with a few arguments,
+# capturing by replaying 1.pcap into the FIFO.
+
+dir=$(dirname "$0")
+action=
+
+while [ $# -gt 0 ]; do
+  case "$1" in
+    --extcap-interfaces) action=interfaces ;;
+    --extcap-dlts) action=dlts ;;
+    --extcap-config) action=config ;;
+    --capture) action=capture ;;
+    --fifo) shift; fifo=$1 ;;
+  esac
+  shift
+done
+
+case "$action" in
+  interfaces)
+    echo "extcap {version=1.0}{help=https://example.com/{docs}}"
+    echo "interface {value=example}{display=Example interface}"
+    ;;
+  dlts)
+    echo "dlt {number=1}{name=EN10MB}{display=Ethernet}"
+    ;;
+  config)
+    echo "arg {number=0}{call=--delay}{display=Delay}{type=integer}{range=1,15}{default=5}{tooltip=Time between packets}"
+    echo "arg {number=1}{call=--message}{display=Message}{type=string}{required=true}"
+    echo "arg {number=2}{call=--verify}{display=Verify}{type=boolflag}"
+    echo "arg {number=3}{call=--remote}{display=Remote}{type=selector}"
+    echo "value {arg=3}{value=if1}{display=Remote 1}{default=true}"
+    echo "value {arg=3}{value=if2}{display=Remote 2}"
+    ;;
+  capture)
+    cat "$dir/1.pcap" > "$fifo"
+    ;;
+  *)
+    exit 1
+    ;;
+esac
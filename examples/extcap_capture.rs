@@ -0,0 +1,81 @@
+extern crate rust_wireshark;
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use rust_wireshark::*;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+  #[arg(short, long)]
+  binary: Option<PathBuf>,
+
+  #[arg(short = 'n', long, default_value_t = 10)]
+  count: usize,
+}
+
+fn main() {
+  let args = Args::parse();
+
+  wtap_init();
+
+  if !epan_init() {
+    panic!("Could not initialize EPAN!");
+  }
+
+  let binaries = match args.binary {
+    Some(binary) => vec![binary],
+    None => find_extcap_binaries(),
+  };
+
+  let mut interfaces = Vec::new();
+
+  for path in binaries {
+    match ExtcapBinary::query(path.clone()) {
+      Ok(binary) => {
+        println!("{} (version {:?})", path.display(), binary.version);
+
+        for interface in binary.interfaces {
+          println!("  {}: {}", interface.value, interface.display);
+
+          for arg in interface.config().unwrap_or_default() {
+            println!(
+              "    {} {:?} default={:?} required={}",
+              arg.call, arg.arg_type, arg.default, arg.required
+            );
+          }
+
+          interfaces.push(interface);
+        }
+      }
+      Err(e) => println!("{}: {:?}", path.display(), e),
+    }
+  }
+
+  let Some(interface) = interfaces.first() else {
+    return;
+  };
+
+  let mut capture = ExtcapCapture::new(interface, &[], None).unwrap();
+
+  let session = Session::new();
+
+  for _ in 0..args.count {
+    let mut rec = match capture.read() {
+      Ok(rec) => rec,
+      Err(e) => {
+        println!("Got error: {:?}", e);
+        break;
+      }
+    };
+
+    let mut prec = session.new_prec(&mut rec);
+
+    prec.dissect();
+
+    for frame in prec.get_frames().unwrap() {
+      println!("{}", frame);
+    }
+  }
+}
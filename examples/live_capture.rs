@@ -0,0 +1,94 @@
+extern crate rust_wireshark;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Parser;
+use rust_wireshark::*;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+  #[arg(short, long, default_value = "lo")]
+  interface: String,
+
+  #[arg(short, long)]
+  capture_filter: Option<String>,
+
+  #[arg(short, long)]
+  display_filter: Option<String>,
+
+  #[arg(short = 'n', long, default_value_t = 10)]
+  count: u64,
+
+  #[arg(short = 'a', long)]
+  duration: Option<u64>,
+
+  #[arg(short, long)]
+  write: Option<PathBuf>,
+}
+
+fn main() {
+  let args = Args::parse();
+
+  wtap_init();
+
+  if !epan_init() {
+    panic!("Could not initialize EPAN!");
+  }
+
+  let options = LiveCaptureOptions {
+    filter: args.capture_filter,
+    stop: StopConditions {
+      packets: Some(args.count),
+      duration: args.duration.map(Duration::from_secs),
+      ..Default::default()
+    },
+    ring_buffer: args.write.map(RingBufferOptions::new),
+    stats_interval: Some(Duration::from_secs(1)),
+    ..Default::default()
+  };
+
+  let mut capture = LiveCapture::new(args.interface, options).unwrap();
+
+  let session = Session::new();
+
+  let dfilter = args.display_filter.map(|f| DFilter::new(f).unwrap());
+
+  loop {
+    let mut rec = match capture.next_event() {
+      Ok(CaptureEvent::Packet(rec)) => rec,
+      Ok(event) => {
+        println!("Event: {:?}", event);
+        continue;
+      }
+      Err(e) => {
+        println!("Got error: {:?}", e);
+        break;
+      }
+    };
+
+    let mut prec = session.new_prec(&mut rec);
+
+    if let Some(ref dfilter) = dfilter {
+      prec.prime_with_dfilter(dfilter);
+    }
+
+    prec.dissect();
+
+    let matches = dfilter.as_ref().is_none_or(|f| f.apply_rec(&prec));
+
+    let frames = prec.get_frames().unwrap();
+
+    println!(
+      "Captured on {}: {} ({})",
+      capture.get_device(),
+      frames
+        .iter()
+        .map(|f| f.get_abbrev())
+        .collect::<Vec<_>>()
+        .join("/"),
+      if matches { "matches" } else { "no match" }
+    );
+  }
+}
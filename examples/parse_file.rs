@@ -22,6 +22,10 @@ fn main() {
   let mut wtap = Wtap::new(args.file).unwrap();
 
   let session = Session::new();
+
+  session.set_columns(&Column::defaults()).unwrap();
+
+  let ctx = session.new_capture_context().unwrap();
   
   let dfilter = DFilter::new("udp.port == 53".to_string()).unwrap();
 
@@ -32,7 +36,7 @@ fn main() {
       Ok(mut rec) => {
         println!("Got record!");
         
-        let mut prec = session.new_prec(&mut rec);
+        let mut prec = ctx.new_prec(&mut rec);
         
         prec.prime_with_dfilter(&dfilter);
 
@@ -40,6 +44,10 @@ fn main() {
         
         let root_node = prec.get_root_node().unwrap();
 
+        println!("Frame number: {}", prec.get_frame_number());
+
+        println!("Columns: {}", prec.columns().unwrap().join(" | "));
+
         if dfilter.apply_rec(&prec) {
           println!("Record MATCHES {}", dfilter);
         } else {
@@ -0,0 +1,76 @@
+extern crate rust_wireshark;
+
+use std::io::Write;
+use std::net::TcpStream;
+
+use clap::Parser;
+use rust_wireshark::*;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+  #[arg(short, long, default_value = "127.0.0.1:0")]
+  listen: String,
+
+  // When set, a local sender replays this file, once per connection.
+  #[arg(short, long)]
+  file: Option<String>,
+
+  #[arg(short, long, default_value_t = 2)]
+  connections: usize,
+}
+
+fn main() {
+  let args = Args::parse();
+
+  wtap_init();
+
+  if !epan_init() {
+    panic!("Could not initialize EPAN!");
+  }
+
+  let options = PcapStreamOptions {
+    max_reconnects: Some(args.connections.saturating_sub(1)),
+    ..Default::default()
+  };
+
+  let mut stream = PcapStream::listen(args.listen, options).unwrap();
+
+  let addr = stream.local_addr().unwrap();
+
+  println!("Listening on {}", addr);
+
+  if let Some(file) = args.file {
+    let data = std::fs::read(file).unwrap();
+
+    std::thread::spawn(move || {
+      for _ in 0..args.connections {
+        let mut sender = TcpStream::connect(addr).unwrap();
+        sender.write_all(&data).unwrap();
+      }
+    });
+  }
+
+  let session = Session::new();
+
+  loop {
+    let mut rec = match stream.read() {
+      Ok(rec) => rec,
+      Err(e) => {
+        println!("Got error: {:?}", e);
+        break;
+      }
+    };
+
+    let mut prec = session.new_prec(&mut rec);
+
+    prec.dissect();
+
+    println!(
+      "{:?} (reconnects: {}): {} frames",
+      stream.peer_addr(),
+      stream.get_reconnects(),
+      prec.get_frames().unwrap().len()
+    );
+  }
+}
@@ -0,0 +1,80 @@
+extern crate rust_wireshark;
+
+use std::collections::HashMap;
+use std::{cell::RefCell, rc::Rc};
+
+use clap::Parser;
+use rust_wireshark::*;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+  #[arg(short, long)]
+  file: String,
+
+  #[arg(short, long)]
+  display_filter: Option<String>,
+}
+
+fn main() {
+  let args = Args::parse();
+
+  wtap_init();
+
+  if !epan_init() {
+    panic!("Could not initialize EPAN!");
+  }
+
+  let streams: Rc<RefCell<HashMap<u32, (String, u64, u64)>>> =
+    Rc::new(RefCell::new(HashMap::new()));
+
+  let tcp_streams = streams.clone();
+
+  let mut listener = TapListener::register(args.display_filter, move |pkt, tcp: &TcpTap| {
+    let mut streams = tcp_streams.borrow_mut();
+
+    let entry = streams.entry(tcp.stream).or_insert_with(|| {
+      (
+        format!("{}:{} -> {}:{}", tcp.src, tcp.srcport, tcp.dst, tcp.dstport),
+        0,
+        0,
+      )
+    });
+
+    entry.1 += 1;
+    entry.2 += pkt.len as u64;
+  })
+  .unwrap();
+
+  let draw_streams = streams.clone();
+
+  listener.on_draw(move || {
+    for (stream, (name, packets, bytes)) in draw_streams.borrow().iter() {
+      println!(
+        "Stream {}: {} ({} packets, {} bytes)",
+        stream, name, packets, bytes
+      );
+    }
+  });
+
+  let mut wtap = Wtap::new(args.file).unwrap();
+
+  let session = Session::new();
+
+  let ctx = session.new_capture_context().unwrap();
+
+  loop {
+    let mut rec = match wtap.read() {
+      Ok(rec) => rec,
+      Err(Error::EOF) => break,
+      Err(e) => {
+        println!("Got error: {:?}", e);
+        break;
+      }
+    };
+
+    ctx.new_prec(&mut rec).dissect();
+  }
+
+  TapListener::draw_all();
+}
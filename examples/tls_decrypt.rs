@@ -0,0 +1,71 @@
+extern crate rust_wireshark;
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use rust_wireshark::*;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+  #[arg(short, long)]
+  file: String,
+
+  #[arg(short, long)]
+  keylog: PathBuf,
+
+  #[arg(short, long, default_value = "http || http2 || http3")]
+  display_filter: String,
+}
+
+fn main() {
+  let args = Args::parse();
+
+  wtap_init();
+
+  if !epan_init() {
+    panic!("Could not initialize EPAN!");
+  }
+
+  let mut wtap = Wtap::new(args.file).unwrap();
+
+  Decryption::set_tls_keylog_file(&args.keylog).unwrap();
+
+  let session = Session::new();
+
+  let ctx = session.new_capture_context().unwrap();
+
+  let dfilter = DFilter::new(args.display_filter).unwrap();
+
+  loop {
+    let mut rec = match wtap.read() {
+      Ok(rec) => rec,
+      Err(Error::EOF) => break,
+      Err(e) => {
+        println!("Got error: {:?}", e);
+        break;
+      }
+    };
+
+    let mut prec = ctx.new_prec(&mut rec);
+
+    prec.prime_with_dfilter(&dfilter);
+    prec.dissect();
+
+    if !dfilter.apply_rec(&prec) {
+      continue;
+    }
+
+    let root_node = prec.get_root_node().unwrap();
+
+    println!(
+      "Frame {}: {:?}",
+      prec.get_frame_number(),
+      root_node
+        .find_hierarchical("http.request.full_uri".to_string())
+        .or_else(|| root_node.find_hierarchical("http.response.code".to_string()))
+        .and_then(|node| node.get_fvalue())
+        .map(|fv| fv.to_string())
+    );
+  }
+}
@@ -0,0 +1,431 @@
+use std::collections::VecDeque;
+use std::ffi::CString;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::ringbuffer::RingBuffer;
+use crate::utils;
+use crate::*;
+
+#[derive(Debug, Clone, Default)]
+pub struct StopConditions {
+  pub duration: Option<Duration>,
+  pub packets: Option<u64>,
+  pub filesize: Option<u64>,
+  pub files: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+  Duration,
+  Packets,
+  FileSize,
+  Files,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureStats {
+  pub received: u32,
+  pub dropped: u32,
+  pub if_dropped: u32,
+}
+
+#[derive(Debug)]
+pub enum CaptureEvent {
+  Packet(WtapRec),
+  Stats(CaptureStats),
+  FileSwitched(PathBuf),
+  Stopped(StopReason),
+}
+
+#[derive(Debug, Clone)]
+pub struct LiveCaptureOptions {
+  pub snaplen: i32,
+  pub promiscuous: bool,
+  pub buffer_size: Option<i32>,
+  pub timeout_ms: i32,
+  pub filter: Option<String>,
+  pub stop: StopConditions,
+  pub ring_buffer: Option<RingBufferOptions>,
+  pub stats_interval: Option<Duration>,
+}
+
+impl Default for LiveCaptureOptions {
+  fn default() -> Self {
+    LiveCaptureOptions {
+      snaplen: 262144,
+      promiscuous: true,
+      buffer_size: None,
+      timeout_ms: 250,
+      filter: None,
+      stop: StopConditions::default(),
+      ring_buffer: None,
+      stats_interval: None,
+    }
+  }
+}
+
+pub struct LiveCapture {
+  device: String,
+  pcap: *mut raw::pcap_t,
+  encap: i32,
+  file_type: i32,
+  offset: raw::gint64,
+  stop: StopConditions,
+  stats_interval: Option<Duration>,
+  ring: Option<RingBuffer>,
+  started: Instant,
+  last_stats: Instant,
+  packets: u64,
+  pending: VecDeque<CaptureEvent>,
+  stopped: bool,
+}
+
+impl Drop for LiveCapture {
+  fn drop(&mut self) {
+    self.ring.take();
+
+    unsafe {
+      raw::pcap_close(self.pcap);
+    }
+  }
+}
+
+impl LiveCapture {
+  pub fn new(device: String, options: LiveCaptureOptions) -> Result<LiveCapture, Error> {
+    let cdevice = CString::new(device.clone()).unwrap();
+
+    let mut errbuf = [0 as raw::gchar; raw::PCAP_ERRBUF_SIZE as usize];
+
+    unsafe {
+      let pcap = raw::pcap_create(cdevice.as_ptr(), errbuf.as_mut_ptr());
+
+      if pcap.is_null() {
+        return Err(Error::CaptureError(utils::cstr_to_string(errbuf.as_ptr())));
+      }
+
+      let mut capture = LiveCapture {
+        device,
+        pcap,
+        encap: raw::WTAP_ENCAP_UNKNOWN as i32,
+        file_type: raw::wtap_pcap_file_type_subtype(),
+        offset: 0,
+        stop: options.stop,
+        stats_interval: options.stats_interval,
+        ring: None,
+        started: Instant::now(),
+        last_stats: Instant::now(),
+        packets: 0,
+        pending: VecDeque::new(),
+        stopped: false,
+      };
+
+      raw::pcap_set_snaplen(pcap, options.snaplen);
+      raw::pcap_set_promisc(pcap, options.promiscuous as ::std::os::raw::c_int);
+      raw::pcap_set_timeout(pcap, options.timeout_ms);
+
+      if let Some(size) = options.buffer_size {
+        raw::pcap_set_buffer_size(pcap, size);
+      }
+
+      let ret = raw::pcap_activate(pcap);
+
+      if ret < 0 {
+        return Err(capture.last_error(ret));
+      }
+
+      if let Some(filter) = options.filter {
+        capture.set_filter(filter)?;
+      }
+
+      capture.encap = raw::wtap_pcap_encap_to_wtap_encap(raw::pcap_datalink(pcap));
+
+      if let Some(ring_options) = options.ring_buffer {
+        let ring = RingBuffer::new(ring_options, capture.encap, raw::pcap_snapshot(pcap))?;
+
+        if let Some(path) = ring.get_current_file() {
+          capture
+            .pending
+            .push_back(CaptureEvent::FileSwitched(path.clone()));
+        }
+
+        capture.ring = Some(ring);
+      }
+
+      capture.started = Instant::now();
+      capture.last_stats = capture.started;
+
+      Ok(capture)
+    }
+  }
+
+  pub fn get_device(&self) -> &str {
+    &self.device
+  }
+
+  pub fn set_filter(&mut self, filter: String) -> Result<(), Error> {
+    let cfilter = unsafe {
+      CaptureFilter::compile(
+        filter,
+        raw::pcap_datalink(self.pcap),
+        raw::pcap_snapshot(self.pcap),
+      )?
+    };
+
+    self.set_capture_filter(&cfilter)
+  }
+
+  pub fn set_capture_filter(&mut self, cfilter: &CaptureFilter) -> Result<(), Error> {
+    unsafe {
+      let ret = raw::pcap_setfilter(
+        self.pcap,
+        (&cfilter.program) as *const raw::bpf_program as *mut raw::bpf_program,
+      );
+
+      if ret < 0 {
+        Err(self.last_error(ret))
+      } else {
+        Ok(())
+      }
+    }
+  }
+
+  pub fn read(&mut self) -> Result<WtapRec, Error> {
+    loop {
+      match self.next_event()? {
+        CaptureEvent::Packet(rec) => return Ok(rec),
+        CaptureEvent::Stopped(_) => return Err(Error::EOF),
+        _ => continue,
+      }
+    }
+  }
+
+  // Once a Stopped event has been returned, further calls return Error::EOF.
+  pub fn next_event(&mut self) -> Result<CaptureEvent, Error> {
+    if self.stopped {
+      return Err(Error::EOF);
+    }
+
+    let mut hdr: *mut raw::pcap_pkthdr = std::ptr::null_mut();
+    let mut data: *const raw::u_char = std::ptr::null();
+
+    loop {
+      if let Some(event) = self.pending.pop_front() {
+        return Ok(event);
+      }
+
+      if let Some(reason) = self.check_stop() {
+        self.stopped = true;
+        self.ring.take();
+
+        return Ok(CaptureEvent::Stopped(reason));
+      }
+
+      if self
+        .stats_interval
+        .is_some_and(|interval| self.last_stats.elapsed() >= interval)
+      {
+        self.last_stats = Instant::now();
+
+        return Ok(CaptureEvent::Stats(self.get_stats()?));
+      }
+
+      let ret = unsafe {
+        raw::pcap_next_ex(
+          self.pcap,
+          (&mut hdr) as *mut *mut raw::pcap_pkthdr,
+          (&mut data) as *mut *const raw::u_char,
+        )
+      };
+
+      match ret {
+        1 => {
+          let rec = self.new_rec(hdr, data);
+
+          self.packets += 1;
+
+          if let Some(ring) = self.ring.as_mut() {
+            if ring.should_switch() {
+              let path = ring.switch_file()?;
+              self.pending.push_back(CaptureEvent::FileSwitched(path));
+            }
+
+            ring.write(&rec)?;
+          }
+
+          // After any FileSwitched, since the packet went to the new file.
+          self.pending.push_back(CaptureEvent::Packet(rec));
+        }
+        // read timeout expired, nothing captured yet
+        0 => continue,
+        raw::PCAP_ERROR_BREAK => return Err(Error::EOF),
+        _ => return Err(self.last_error(ret)),
+      }
+    }
+  }
+
+  pub fn get_stats(&self) -> Result<CaptureStats, Error> {
+    unsafe {
+      let mut stats: raw::pcap_stat = std::mem::zeroed();
+
+      let ret = raw::pcap_stats(self.pcap, (&mut stats) as *mut raw::pcap_stat);
+
+      if ret < 0 {
+        return Err(self.last_error(ret));
+      }
+
+      Ok(CaptureStats {
+        received: stats.ps_recv,
+        dropped: stats.ps_drop,
+        if_dropped: stats.ps_ifdrop,
+      })
+    }
+  }
+
+  pub fn get_current_file(&self) -> Option<PathBuf> {
+    self
+      .ring
+      .as_ref()
+      .and_then(|r| r.get_current_file().cloned())
+  }
+
+  fn check_stop(&self) -> Option<StopReason> {
+    let stop = &self.stop;
+
+    if stop.packets.is_some_and(|packets| self.packets >= packets) {
+      return Some(StopReason::Packets);
+    }
+
+    if stop
+      .duration
+      .is_some_and(|duration| self.started.elapsed() >= duration)
+    {
+      return Some(StopReason::Duration);
+    }
+
+    let written = match &self.ring {
+      Some(ring) => ring.get_bytes_written(),
+      None => self.offset as u64,
+    };
+
+    if stop.filesize.is_some_and(|size| written >= size) {
+      return Some(StopReason::FileSize);
+    }
+
+    if let Some(ring) = &self.ring
+      && stop
+        .files
+        .is_some_and(|files| ring.get_files_created() >= files && ring.should_switch())
+    {
+      return Some(StopReason::Files);
+    }
+
+    None
+  }
+
+  fn new_rec(&mut self, hdr: *const raw::pcap_pkthdr, data: *const raw::u_char) -> WtapRec {
+    let mut rec = WtapRec::new();
+
+    rec.set_file_type(self.file_type);
+    rec.offset = self.offset;
+
+    let caplen = unsafe {
+      let mut inner = rec.rec.borrow_mut();
+      let wrec = &mut inner.rec;
+
+      raw::wtap_setup_packet_rec(wrec as *mut raw::wtap_rec, self.encap);
+
+      wrec.presence_flags = raw::WTAP_HAS_TS | raw::WTAP_HAS_CAP_LEN;
+      wrec.tsprec = raw::WTAP_TSPREC_USEC as i32;
+      wrec.ts.secs = (*hdr).ts.tv_sec;
+      wrec.ts.nsecs = ((*hdr).ts.tv_usec * 1000) as i32;
+      wrec.rec_header.packet_header.caplen = (*hdr).caplen;
+      wrec.rec_header.packet_header.len = (*hdr).len;
+
+      raw::ws_buffer_append(
+        (&mut wrec.data) as *mut raw::Buffer,
+        data,
+        (*hdr).caplen as usize,
+      );
+
+      (*hdr).caplen
+    };
+
+    self.offset += caplen as raw::gint64;
+
+    rec
+  }
+
+  fn last_error(&self, status: ::std::os::raw::c_int) -> Error {
+    unsafe {
+      let msg = utils::cstr_to_string(raw::pcap_geterr(self.pcap));
+
+      if msg.is_empty() {
+        Error::CaptureError(utils::cstr_to_string(raw::pcap_statustostr(status)))
+      } else {
+        Error::CaptureError(msg)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::net::UdpSocket;
+
+  #[test]
+  fn capture_loopback() {
+    let _guard = utils::init_for_tests();
+
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = receiver.local_addr().unwrap().port();
+
+    let options = LiveCaptureOptions {
+      filter: Some(format!("udp dst port {}", port)),
+      stop: StopConditions {
+        packets: Some(3),
+        duration: Some(Duration::from_secs(10)),
+        ..StopConditions::default()
+      },
+      ..LiveCaptureOptions::default()
+    };
+
+    // Capturing needs CAP_NET_RAW, which unprivileged test runs lack.
+    let mut capture = match LiveCapture::new("lo".to_string(), options) {
+      Ok(capture) => capture,
+      Err(Error::CaptureError(msg)) if msg.contains("permission") => {
+        eprintln!("skipping loopback capture: {}", msg);
+        return;
+      }
+      Err(e) => panic!("{:?}", e),
+    };
+
+    for payload in [&b"one"[..], b"two", b"three"] {
+      sender.send_to(payload, ("127.0.0.1", port)).unwrap();
+    }
+
+    let mut payloads = Vec::new();
+
+    loop {
+      match capture.next_event().unwrap() {
+        CaptureEvent::Packet(rec) => {
+          assert_eq!(rec.get_caplen(), rec.get_len());
+          payloads.push(rec.get_data());
+        }
+        CaptureEvent::Stopped(reason) => {
+          assert_eq!(reason, StopReason::Packets);
+          break;
+        }
+        _ => {}
+      }
+    }
+
+    assert_eq!(payloads.len(), 3);
+    assert!(payloads[0].ends_with(b"one"));
+    assert!(payloads[1].ends_with(b"two"));
+    assert!(payloads[2].ends_with(b"three"));
+    assert!(matches!(capture.next_event(), Err(Error::EOF)));
+  }
+}
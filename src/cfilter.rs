@@ -0,0 +1,188 @@
+use std::ffi::CString;
+use std::fmt;
+
+use crate::Error;
+use crate::WtapRec;
+use crate::raw;
+use crate::utils;
+
+pub struct CaptureFilter {
+  pub(crate) program: raw::bpf_program,
+  filter_str: String,
+  linktype: i32,
+  snaplen: i32,
+}
+
+impl Drop for CaptureFilter {
+  fn drop(&mut self) {
+    unsafe {
+      raw::pcap_freecode((&mut self.program) as *mut raw::bpf_program);
+    }
+  }
+}
+
+impl fmt::Display for CaptureFilter {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "CaptureFilter {{ filter: '{}', linktype: {} }}",
+      self.filter_str, self.linktype
+    )
+  }
+}
+
+impl fmt::Debug for CaptureFilter {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "CaptureFilter {{ filter: '{}', linktype: {}, snaplen: {}, insns: {} }}",
+      self.filter_str, self.linktype, self.snaplen, self.program.bf_len
+    )
+  }
+}
+
+impl CaptureFilter {
+  pub fn compile(filter_str: String, linktype: i32, snaplen: i32) -> Result<CaptureFilter, Error> {
+    let cfilter = CString::new(filter_str.clone())
+      .map_err(|_| Error::InvalidCaptureFilter("filter contains a NUL byte".to_string()))?;
+
+    unsafe {
+      let pcap = raw::pcap_open_dead(linktype, snaplen);
+
+      if pcap.is_null() {
+        return Err(Error::InvalidCaptureFilter(format!(
+          "cannot compile filters for link-layer type {}",
+          linktype
+        )));
+      }
+
+      let mut program: raw::bpf_program = std::mem::zeroed();
+
+      let ret = raw::pcap_compile(
+        pcap,
+        (&mut program) as *mut raw::bpf_program,
+        cfilter.as_ptr(),
+        1,
+        raw::PCAP_NETMASK_UNKNOWN,
+      );
+
+      let result = if ret < 0 {
+        Err(Error::InvalidCaptureFilter(utils::cstr_to_string(
+          raw::pcap_geterr(pcap),
+        )))
+      } else {
+        Ok(CaptureFilter {
+          program,
+          filter_str,
+          linktype,
+          snaplen,
+        })
+      };
+
+      raw::pcap_close(pcap);
+
+      result
+    }
+  }
+
+  pub fn get_filter(&self) -> &str {
+    &self.filter_str
+  }
+
+  pub fn get_linktype(&self) -> i32 {
+    self.linktype
+  }
+
+  pub fn get_snaplen(&self) -> i32 {
+    self.snaplen
+  }
+
+  // One line per instruction, in the format of `tcpdump -d`.
+  pub fn dump(&self) -> Vec<String> {
+    (0..self.program.bf_len)
+      .map(|idx| unsafe {
+        utils::cstr_to_string(raw::bpf_image(
+          self.program.bf_insns.add(idx as usize),
+          idx as ::std::os::raw::c_int,
+        ))
+      })
+      .collect()
+  }
+
+  pub fn apply_bytes(&self, data: &[u8], wire_len: u32) -> bool {
+    unsafe {
+      let mut hdr: raw::pcap_pkthdr = std::mem::zeroed();
+
+      hdr.caplen = data.len() as raw::bpf_u_int32;
+      hdr.len = wire_len;
+
+      raw::pcap_offline_filter(
+        (&self.program) as *const raw::bpf_program,
+        (&hdr) as *const raw::pcap_pkthdr,
+        data.as_ptr(),
+      ) != 0
+    }
+  }
+
+  // Only packet records can match, and only if their encapsulation maps to
+  // the filter's link-layer type.
+  pub fn apply_rec(&self, rec: &WtapRec) -> bool {
+    if !rec.is_packet() {
+      return false;
+    }
+
+    let linktype = unsafe { raw::wtap_wtap_encap_to_pcap_encap(rec.get_encap()) };
+
+    if linktype != self.linktype {
+      return false;
+    }
+
+    rec.with_data(|data| self.apply_bytes(data, rec.get_len()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Wtap;
+
+  fn matching(filter: &str, linktype: i32) -> Vec<usize> {
+    let cfilter = CaptureFilter::compile(filter.to_string(), linktype, 262144).unwrap();
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/data/1.pcap");
+    let mut wtap = Wtap::new(path.to_string()).unwrap();
+    let mut matched = Vec::new();
+    let mut num = 0;
+
+    while let Ok(rec) = wtap.read() {
+      num += 1;
+
+      if cfilter.apply_rec(&rec) {
+        matched.push(num);
+      }
+    }
+
+    assert_eq!(num, 6);
+
+    matched
+  }
+
+  #[test]
+  fn apply_rec_offline() {
+    let _guard = utils::init_for_tests();
+
+    // DNS queries over loopback, answered in frames 2, 5 and 6.
+    assert_eq!(matching("udp src port 53", 1), vec![2, 5, 6]);
+    assert_eq!(matching("udp dst port 53", 1), vec![1, 3, 4]);
+    assert_eq!(matching("tcp", 1), Vec::<usize>::new());
+    // An Ethernet capture never matches a filter compiled for raw IP.
+    assert_eq!(matching("ip", 101), Vec::<usize>::new());
+  }
+
+  #[test]
+  fn invalid_filter() {
+    assert!(matches!(
+      CaptureFilter::compile("udp port".to_string(), 1, 262144),
+      Err(Error::InvalidCaptureFilter(_))
+    ));
+  }
+}
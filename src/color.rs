@@ -0,0 +1,85 @@
+use crate::utils;
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+  pub red: u16,
+  pub green: u16,
+  pub blue: u16,
+}
+
+impl From<raw::color_t> for Color {
+  fn from(color: raw::color_t) -> Self {
+    Color {
+      red: color.red,
+      green: color.green,
+      blue: color.blue,
+    }
+  }
+}
+
+// The colouring rule of the profile's colorfilters file that a packet
+// matched first, as shown in Wireshark's packet list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorRule {
+  pub name: String,
+  pub filter: String,
+  pub foreground: Color,
+  pub background: Color,
+}
+
+// Reads the colouring rules of the current profile, falling back to the
+// global and then the default ones as Wireshark does.
+pub(crate) fn init_color_filters() -> Result<(), Error> {
+  unsafe {
+    let mut errmsg: *mut raw::gchar = std::ptr::null_mut();
+
+    if !raw::color_filters_init((&mut errmsg) as *mut *mut raw::gchar, None) {
+      let msg = utils::opt_cstr_to_string(errmsg).unwrap_or_default();
+      raw::g_free(errmsg as raw::gpointer);
+
+      return Err(Error::Profile(msg));
+    }
+  }
+
+  Ok(())
+}
+
+pub(crate) unsafe fn prime_edt(edt: *mut raw::epan_dissect_t) {
+  unsafe {
+    if raw::color_filters_used() {
+      raw::color_filters_prime_edt(edt);
+    }
+  }
+}
+
+pub(crate) unsafe fn colorize(edt: *mut raw::epan_dissect_t) -> Option<ColorRule> {
+  unsafe {
+    if !raw::color_filters_used() {
+      return None;
+    }
+
+    let colorf = raw::color_filters_colorize_packet(edt);
+
+    if colorf.is_null() {
+      return None;
+    }
+
+    Some(ColorRule {
+      name: utils::opt_cstr_to_string((*colorf).filter_name).unwrap_or_default(),
+      filter: utils::opt_cstr_to_string((*colorf).filter_text).unwrap_or_default(),
+      foreground: (*colorf).fg_color.into(),
+      background: (*colorf).bg_color.into(),
+    })
+  }
+}
+
+impl ParsedRec {
+  // None when no colouring rule matched or none are loaded.
+  pub fn color_rule(&self) -> Result<Option<ColorRule>, Error> {
+    match self.color_rule {
+      None => Err(Error::NotDissected),
+      Some(ref rule) => Ok(rule.clone()),
+    }
+  }
+}
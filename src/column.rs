@@ -0,0 +1,206 @@
+use std::ffi::CString;
+
+use crate::utils;
+use crate::*;
+
+#[derive(Debug, Clone)]
+pub enum Column {
+  Number,
+  Time,
+  Source,
+  Destination,
+  Protocol,
+  Length,
+  Info,
+  // A display filter field expression such as "http.host || dns.qry.name".
+  // `occurrence` 0 shows all occurrences, negative values count from the
+  // end.
+  Custom {
+    title: String,
+    fields: String,
+    occurrence: i32,
+  },
+  // Any other Wireshark column format, e.g. "%uns" for unresolved source.
+  Format {
+    title: String,
+    format: String,
+  },
+}
+
+impl Column {
+  // The standard Wireshark packet list.
+  pub fn defaults() -> Vec<Column> {
+    vec![
+      Column::Number,
+      Column::Time,
+      Column::Source,
+      Column::Destination,
+      Column::Protocol,
+      Column::Length,
+      Column::Info,
+    ]
+  }
+
+  pub fn get_title(&self) -> String {
+    match self {
+      Column::Number => "No.".to_string(),
+      Column::Time => "Time".to_string(),
+      Column::Source => "Source".to_string(),
+      Column::Destination => "Destination".to_string(),
+      Column::Protocol => "Protocol".to_string(),
+      Column::Length => "Length".to_string(),
+      Column::Info => "Info".to_string(),
+      Column::Custom { title, .. } => title.clone(),
+      Column::Format { title, .. } => title.clone(),
+    }
+  }
+
+  // Custom columns have no format string of their own.
+  fn get_format(&self) -> Option<String> {
+    match self {
+      Column::Number => Some("%m".to_string()),
+      Column::Time => Some("%t".to_string()),
+      Column::Source => Some("%s".to_string()),
+      Column::Destination => Some("%d".to_string()),
+      Column::Protocol => Some("%p".to_string()),
+      Column::Length => Some("%L".to_string()),
+      Column::Info => Some("%i".to_string()),
+      Column::Custom { .. } => None,
+      Column::Format { format, .. } => Some(format.clone()),
+    }
+  }
+
+  fn get_format_id(&self) -> Result<i32, Error> {
+    let Some(format) = self.get_format() else {
+      return Ok(raw::COL_CUSTOM as i32);
+    };
+
+    let cformat =
+      CString::new(format.as_str()).map_err(|_| Error::InvalidColumn(format.clone()))?;
+
+    match unsafe { raw::get_column_format_from_str(cformat.as_ptr()) } {
+      -1 => Err(Error::InvalidColumn(format)),
+      id => Ok(id),
+    }
+  }
+}
+
+// Fills the column_info the way build_column_format_array() does, but from
+// `columns` rather than the global gui.column.format preference, so that
+// other sessions and the saved profile keep their own layout.
+pub(crate) fn build_columns(columns: &[Column]) -> Result<Box<raw::column_info>, Error> {
+  // Time columns stay empty until a timestamp type is set; default to what
+  // tshark shows unless the caller already chose one.
+  unsafe {
+    if raw::timestamp_get_type() == raw::ts_type_TS_NOT_SET {
+      raw::timestamp_set_type(raw::ts_type_TS_RELATIVE);
+      raw::timestamp_set_precision(raw::ts_precision_TS_PREC_AUTO as i32);
+    }
+  }
+
+  let mut items = Vec::new();
+
+  for col in columns {
+    let title = col.get_title();
+    let ctitle = CString::new(title.as_str()).map_err(|_| Error::InvalidColumn(title))?;
+
+    let cfields = match col {
+      Column::Custom { fields, .. } => {
+        Some(CString::new(fields.as_str()).map_err(|_| Error::InvalidColumn(fields.clone()))?)
+      }
+      _ => None,
+    };
+
+    items.push((col.get_format_id()?, ctitle, cfields));
+  }
+
+  unsafe {
+    let mut cinfo: Box<raw::column_info> = Box::new(std::mem::zeroed());
+    let cinfo_ptr = cinfo.as_mut() as *mut raw::column_info;
+
+    raw::col_setup(cinfo_ptr, columns.len() as i32);
+
+    for (i, (col, (format_id, ctitle, cfields))) in columns.iter().zip(items).enumerate() {
+      let col_item = &mut *cinfo.columns.add(i);
+
+      *col_item = std::mem::zeroed();
+
+      // The strings are freed by col_cleanup().
+      col_item.col_fmt = format_id;
+      col_item.col_title = raw::g_strdup(ctitle.as_ptr());
+
+      if let (Column::Custom { occurrence, .. }, Some(cfields)) = (col, cfields) {
+        col_item.col_custom_fields = raw::g_strdup(cfields.as_ptr());
+        col_item.col_custom_occurrence = *occurrence;
+        col_item.col_resolved = true;
+      }
+    }
+
+    raw::col_finalize(cinfo_ptr);
+
+    Ok(cinfo)
+  }
+}
+
+pub(crate) fn column_values(cinfo: *const raw::column_info) -> Vec<String> {
+  unsafe {
+    (0..(*cinfo).num_cols as usize)
+      .map(|i| {
+        let col_item = (*cinfo).columns.add(i);
+
+        utils::opt_cstr_to_string((*col_item).col_data).unwrap_or_default()
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils;
+
+  #[test]
+  fn time_and_custom_columns() {
+    let _guard = utils::init_for_tests();
+
+    let session = Session::new();
+
+    session
+      .set_columns(&[
+        Column::Number,
+        Column::Time,
+        Column::Custom {
+          title: "Type".to_string(),
+          fields: "dns.qry.type".to_string(),
+          occurrence: 0,
+        },
+      ])
+      .unwrap();
+
+    let ctx = session.new_capture_context().unwrap();
+    let mut wtap =
+      Wtap::new(concat!(env!("CARGO_MANIFEST_DIR"), "/data/1.pcap").to_string()).unwrap();
+    let mut rows = Vec::new();
+
+    while let Ok(mut rec) = wtap.read() {
+      let mut prec = ctx.new_prec(&mut rec);
+
+      prec.dissect();
+      rows.push(prec.columns().unwrap().join(" "));
+    }
+
+    // Relative to the first frame, and with query types resolved to their
+    // names.
+    assert_eq!(
+      rows,
+      vec![
+        "1 0.000000 A",
+        "2 0.017668 A",
+        "3 1.621167 A",
+        "4 1.621228 AAAA",
+        "5 1.622136 A",
+        "6 1.639326 AAAA",
+      ]
+    );
+  }
+}
@@ -0,0 +1,192 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::*;
+
+// Owns the frame_data of every frame dissected so far, the way tshark's
+// capture_file does, so that frame numbers, time references and the
+// per-frame state dissectors keep across packets stay valid.
+pub(crate) struct InnerCaptureContext {
+  frames: Vec<Box<raw::frame_data>>,
+  cum_bytes: u32,
+  elapsed_time: raw::nstime_t,
+  ref_frame: *const raw::frame_data,
+  prev_dis: *const raw::frame_data,
+}
+
+impl Drop for InnerCaptureContext {
+  fn drop(&mut self) {
+    for fdata in self.frames.iter_mut() {
+      unsafe {
+        raw::frame_data_destroy(fdata.as_mut() as *mut raw::frame_data);
+      }
+    }
+  }
+}
+
+impl InnerCaptureContext {
+  fn new() -> InnerCaptureContext {
+    InnerCaptureContext {
+      frames: Vec::new(),
+      cum_bytes: 0,
+      elapsed_time: raw::nstime_t { secs: 0, nsecs: 0 },
+      ref_frame: std::ptr::null(),
+      prev_dis: std::ptr::null(),
+    }
+  }
+
+  pub(crate) fn get_frame(&mut self, num: u32) -> Option<*mut raw::frame_data> {
+    self
+      .frames
+      .get_mut((num as usize).checked_sub(1)?)
+      .map(|fdata| fdata.as_mut() as *mut raw::frame_data)
+  }
+
+  // Frames are boxed, so the timestamp stays put as more are added.
+  pub(crate) fn get_frame_ts(&self, num: u32) -> Option<*const raw::nstime_t> {
+    self
+      .frames
+      .get((num as usize).checked_sub(1)?)
+      .map(|fdata| (&fdata.abs_ts) as *const raw::nstime_t)
+  }
+
+  pub(crate) fn new_frame(
+    &mut self,
+    rec: *mut raw::wtap_rec,
+    offset: raw::gint64,
+  ) -> *mut raw::frame_data {
+    unsafe {
+      let mut fdata: Box<raw::frame_data> = Box::new(std::mem::zeroed());
+      let num = self.frames.len() as u32 + 1;
+
+      raw::frame_data_init(
+        fdata.as_mut() as *mut raw::frame_data,
+        num,
+        rec,
+        offset,
+        self.cum_bytes,
+      );
+
+      raw::frame_data_set_before_dissect(
+        fdata.as_mut() as *mut raw::frame_data,
+        (&mut self.elapsed_time) as *mut raw::nstime_t,
+        (&mut self.ref_frame) as *mut *const raw::frame_data,
+        self.prev_dis,
+      );
+
+      self.frames.push(fdata);
+
+      self.frames.last_mut().unwrap().as_mut() as *mut raw::frame_data
+    }
+  }
+
+  pub(crate) fn finish_frame(&mut self, fdata: *mut raw::frame_data) {
+    unsafe {
+      raw::frame_data_set_after_dissect(fdata, (&mut self.cum_bytes) as *mut u32);
+    }
+
+    self.prev_dis = fdata;
+  }
+
+  pub(crate) fn get_frame_count(&self) -> u32 {
+    self.frames.len() as u32
+  }
+}
+
+pub struct CaptureContext {
+  pub(crate) session: Rc<RefCell<InnerEpanSession>>,
+  pub(crate) inner: Rc<RefCell<InnerCaptureContext>>,
+}
+
+impl CaptureContext {
+  // Conversation and reassembly state is kept per session and follows one
+  // sequence of frame numbers, so a session only ever has one context. Use a
+  // new Session for each capture.
+  pub fn new(session: &Session) -> Result<CaptureContext, Error> {
+    let mut epan = session.epan.borrow_mut();
+
+    if epan.has_context {
+      return Err(Error::ContextExists);
+    }
+
+    epan.has_context = true;
+    epan.provider.clear_frame_cache();
+
+    Ok(CaptureContext {
+      session: session.epan.clone(),
+      inner: Rc::new(RefCell::new(InnerCaptureContext::new())),
+    })
+  }
+
+  pub fn new_prec(&self, rec: &mut WtapRec) -> ParsedRec {
+    ParsedRec::new(self.session.clone(), rec, Some(self.inner.clone()))
+  }
+
+  pub fn get_frame_count(&self) -> u32 {
+    self.inner.borrow().get_frame_count()
+  }
+}
+
+// Dissects every record of `path` as the session's capture context, with
+// `dfilters` primed, for the statistics computed over a whole file. The
+// file is opened first so a bad path leaves the session without a context.
+pub(crate) fn dissect_file(
+  session: &Session,
+  path: String,
+  dfilters: &[&DFilter],
+  mut f: impl FnMut(&ParsedRec) -> Result<(), Error>,
+) -> Result<(), Error> {
+  let mut wtap = Wtap::new(path).map_err(Error::WsError)?;
+  let ctx = session.new_capture_context()?;
+
+  loop {
+    let mut rec = match wtap.read() {
+      Ok(rec) => rec,
+      Err(Error::EOF) => return Ok(()),
+      Err(e) => return Err(e),
+    };
+
+    let mut prec = ctx.new_prec(&mut rec);
+
+    for dfilter in dfilters {
+      prec.prime_with_dfilter(dfilter);
+    }
+
+    prec.dissect();
+
+    f(&prec)?;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils;
+
+  #[test]
+  fn one_context_per_session() {
+    let _guard = utils::init_for_tests();
+
+    let session = Session::new();
+    let ctx = session.new_capture_context().unwrap();
+    let mut wtap =
+      Wtap::new(concat!(env!("CARGO_MANIFEST_DIR"), "/data/1.pcap").to_string()).unwrap();
+    let mut nums = Vec::new();
+
+    while let Ok(mut rec) = wtap.read() {
+      let mut prec = ctx.new_prec(&mut rec);
+
+      prec.dissect();
+      nums.push(prec.get_frame_number());
+    }
+
+    assert_eq!(nums, vec![1, 2, 3, 4, 5, 6]);
+    assert_eq!(ctx.get_frame_count(), 6);
+
+    drop(ctx);
+
+    assert!(matches!(
+      session.new_capture_context(),
+      Err(Error::ContextExists)
+    ));
+  }
+}
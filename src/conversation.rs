@@ -0,0 +1,317 @@
+use std::ffi::CString;
+use std::os::raw::c_void;
+
+use crate::utils;
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConversationType {
+  Ethernet,
+  Ipv4,
+  Ipv6,
+  Tcp,
+  Udp,
+}
+
+impl ConversationType {
+  fn get_proto_name(&self) -> &'static str {
+    match self {
+      ConversationType::Ethernet => "eth",
+      ConversationType::Ipv4 => "ip",
+      ConversationType::Ipv6 => "ipv6",
+      ConversationType::Tcp => "tcp",
+      ConversationType::Udp => "udp",
+    }
+  }
+
+  fn has_ports(&self) -> bool {
+    matches!(self, ConversationType::Tcp | ConversationType::Udp)
+  }
+
+  fn get_ct(&self) -> Result<*mut raw::register_ct_t, Error> {
+    let name = CString::new(self.get_proto_name()).unwrap();
+
+    unsafe {
+      let proto_id = raw::proto_get_id_by_filter_name(name.as_ptr());
+      let ct = raw::get_conversation_by_proto_id(proto_id);
+
+      if proto_id == -1 || ct.is_null() {
+        Err(Error::Tap(format!(
+          "no conversation table for {}",
+          self.get_proto_name()
+        )))
+      } else {
+        Ok(ct)
+      }
+    }
+  }
+}
+
+// One row of Statistics -> Conversations. A is the side that sent the
+// first packet.
+#[derive(Debug, Clone)]
+pub struct Conversation {
+  pub address_a: String,
+  pub port_a: Option<u32>,
+  pub address_b: String,
+  pub port_b: Option<u32>,
+  pub packets_a_to_b: u64,
+  pub bytes_a_to_b: u64,
+  pub packets_b_to_a: u64,
+  pub bytes_b_to_a: u64,
+  // Seconds since the first packet of the capture.
+  pub rel_start: f64,
+  pub duration: f64,
+}
+
+// One row of Statistics -> Endpoints.
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+  pub address: String,
+  pub port: Option<u32>,
+  pub tx_packets: u64,
+  pub tx_bytes: u64,
+  pub rx_packets: u64,
+  pub rx_bytes: u64,
+}
+
+// Collects conversations from every packet dissected while the table is
+// alive. Start times count from the first record of the CaptureContext the
+// packets went through; records dissected without one all start at 0.
+pub struct ConversationTable {
+  ctype: ConversationType,
+  // Removed before the hash it fills is freed.
+  _listener: TapListener,
+  hash: Box<raw::conv_hash_t>,
+}
+
+impl Drop for ConversationTable {
+  fn drop(&mut self) {
+    unsafe {
+      raw::reset_conversation_table_data(self.hash.as_mut() as *mut raw::conv_hash_t);
+    }
+  }
+}
+
+impl ConversationTable {
+  pub fn new(ctype: ConversationType, filter: Option<String>) -> Result<ConversationTable, Error> {
+    let ct = ctype.get_ct()?;
+
+    let packet_func = unsafe { raw::get_conversation_packet_func(ct) };
+    let (hash, listener) = register_table(ctype, filter, packet_func)?;
+
+    Ok(ConversationTable {
+      ctype,
+      _listener: listener,
+      hash,
+    })
+  }
+
+  pub fn conversations(&self) -> Vec<Conversation> {
+    let items = unsafe { table_items::<raw::conv_item_t>(self.hash.as_ref()) };
+
+    items
+      .iter()
+      .map(|item| {
+        let start = NsTime::from(item.start_time);
+        let stop = NsTime::from(item.stop_time);
+
+        Conversation {
+          address_a: utils::address_to_string(&item.src_address),
+          port_a: self.ctype.has_ports().then_some(item.src_port),
+          address_b: utils::address_to_string(&item.dst_address),
+          port_b: self.ctype.has_ports().then_some(item.dst_port),
+          packets_a_to_b: item.tx_frames,
+          bytes_a_to_b: item.tx_bytes,
+          packets_b_to_a: item.rx_frames,
+          bytes_b_to_a: item.rx_bytes,
+          rel_start: start.as_secs_f64(),
+          duration: (stop.secs - start.secs) as f64 + (stop.nsecs - start.nsecs) as f64 / 1e9,
+        }
+      })
+      .collect()
+  }
+}
+
+pub struct EndpointTable {
+  ctype: ConversationType,
+  // Removed before the hash it fills is freed.
+  _listener: TapListener,
+  hash: Box<raw::conv_hash_t>,
+}
+
+impl Drop for EndpointTable {
+  fn drop(&mut self) {
+    unsafe {
+      raw::reset_endpoint_table_data(self.hash.as_mut() as *mut raw::conv_hash_t);
+    }
+  }
+}
+
+impl EndpointTable {
+  pub fn new(ctype: ConversationType, filter: Option<String>) -> Result<EndpointTable, Error> {
+    let ct = ctype.get_ct()?;
+
+    let packet_func = unsafe { raw::get_endpoint_packet_func(ct) };
+    let (hash, listener) = register_table(ctype, filter, packet_func)?;
+
+    Ok(EndpointTable {
+      ctype,
+      _listener: listener,
+      hash,
+    })
+  }
+
+  pub fn endpoints(&self) -> Vec<Endpoint> {
+    let items = unsafe { table_items::<raw::endpoint_item_t>(self.hash.as_ref()) };
+
+    items
+      .iter()
+      .map(|item| Endpoint {
+        address: utils::address_to_string(&item.myaddress),
+        port: self.ctype.has_ports().then_some(item.port),
+        tx_packets: item.tx_frames,
+        tx_bytes: item.tx_bytes,
+        rx_packets: item.rx_frames,
+        rx_bytes: item.rx_bytes,
+      })
+      .collect()
+  }
+}
+
+// The packet functions of conversation_table.h fill the conv_hash_t they
+// get as tap data, so they run from a listener that owns it.
+fn register_table(
+  ctype: ConversationType,
+  filter: Option<String>,
+  packet_func: raw::tap_packet_cb,
+) -> Result<(Box<raw::conv_hash_t>, TapListener), Error> {
+  let Some(packet_func) = packet_func else {
+    return Err(Error::Tap(format!(
+      "no packet function for {}",
+      ctype.get_proto_name()
+    )));
+  };
+
+  let mut hash: Box<raw::conv_hash_t> = Box::new(unsafe { std::mem::zeroed() });
+  let hash_ptr = hash.as_mut() as *mut raw::conv_hash_t as *mut c_void;
+
+  let listener = TapListener::register_raw(
+    ctype.get_proto_name(),
+    filter,
+    0,
+    Box::new(move |pkt, edt, data, flags| unsafe {
+      packet_func(hash_ptr, pkt.pinfo, edt, data, flags);
+    }),
+  )?;
+
+  Ok((hash, listener))
+}
+
+unsafe fn table_items<'a, T>(hash: &'a raw::conv_hash_t) -> &'a [T] {
+  if hash.conv_array.is_null() {
+    return &[];
+  }
+
+  unsafe {
+    let array = &*hash.conv_array;
+
+    std::slice::from_raw_parts(array.data as *const T, array.len as usize)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn dissect_dns(session: &Session) {
+    let ctx = session.new_capture_context().unwrap();
+    let mut wtap =
+      Wtap::new(concat!(env!("CARGO_MANIFEST_DIR"), "/data/1.pcap").to_string()).unwrap();
+
+    while let Ok(mut rec) = wtap.read() {
+      ctx.new_prec(&mut rec).dissect();
+    }
+  }
+
+  fn assert_secs(actual: f64, expected: f64) {
+    assert!(
+      (actual - expected).abs() < 1e-6,
+      "{} != {}",
+      actual,
+      expected
+    );
+  }
+
+  // data/1.pcap holds two exchanges with a stub resolver, one query from
+  // port 58125 and two from port 55503.
+  #[test]
+  fn udp_conversations() {
+    let _guard = utils::init_for_tests();
+
+    let table = ConversationTable::new(ConversationType::Udp, None).unwrap();
+
+    dissect_dns(&Session::new());
+
+    let convs = table.conversations();
+
+    assert_eq!(convs.len(), 2);
+
+    let first = &convs[0];
+
+    assert_eq!(
+      (first.address_a.as_str(), first.port_a),
+      ("127.0.0.1", Some(58125))
+    );
+    assert_eq!(
+      (first.address_b.as_str(), first.port_b),
+      ("127.0.0.53", Some(53))
+    );
+    assert_eq!((first.packets_a_to_b, first.bytes_a_to_b), (1, 90));
+    assert_eq!((first.packets_b_to_a, first.bytes_b_to_a), (1, 153));
+    assert_secs(first.rel_start, 0.0);
+    assert_secs(first.duration, 0.017668);
+
+    let second = &convs[1];
+
+    assert_eq!(second.port_a, Some(55503));
+    assert_eq!((second.packets_a_to_b, second.bytes_a_to_b), (2, 180));
+    assert_eq!((second.packets_b_to_a, second.bytes_b_to_a), (2, 334));
+    assert_secs(second.rel_start, 1.621167);
+    assert_secs(second.duration, 0.018159);
+  }
+
+  #[test]
+  fn ip_endpoints() {
+    let _guard = utils::init_for_tests();
+
+    let table = EndpointTable::new(ConversationType::Ipv4, None).unwrap();
+
+    dissect_dns(&Session::new());
+
+    let endpoints = table.endpoints();
+
+    assert_eq!(endpoints.len(), 2);
+
+    let client = endpoints.iter().find(|e| e.address == "127.0.0.1").unwrap();
+
+    assert_eq!(client.port, None);
+    assert_eq!((client.tx_packets, client.tx_bytes), (3, 270));
+    assert_eq!((client.rx_packets, client.rx_bytes), (3, 487));
+  }
+
+  #[test]
+  fn filtered_conversations() {
+    let _guard = utils::init_for_tests();
+
+    let table =
+      ConversationTable::new(ConversationType::Udp, Some("udp.port == 55503".to_string())).unwrap();
+
+    dissect_dns(&Session::new());
+
+    let convs = table.conversations();
+
+    assert_eq!(convs.len(), 1);
+    assert_eq!(convs[0].packets_a_to_b + convs[0].packets_b_to_a, 4);
+  }
+}
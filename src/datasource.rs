@@ -0,0 +1,204 @@
+use std::pin::Pin;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::proto::tvb_bytes;
+use crate::utils;
+use crate::*;
+
+// A buffer fields can point into: the frame itself, or data a dissector
+// built from it, such as a reassembled stream, decrypted TLS records or a
+// decompressed HTTP body.
+#[derive(Debug, Clone)]
+pub struct DataSource {
+  // As shown in Wireshark's byte view tabs, e.g. "Reassembled TCP (1460 bytes)".
+  pub name: String,
+  pub data: Vec<u8>,
+  // Keeps the dissection, and with it the tvb, alive.
+  prec: Rc<RefCell<Pin<Box<Edt>>>>,
+  pub(crate) tvb: *mut raw::tvbuff_t,
+}
+
+impl DataSource {
+  pub fn contains(&self, range: &ByteRange) -> bool {
+    self.tvb == range.tvb
+  }
+
+  fn new(prec: &Rc<RefCell<Pin<Box<Edt>>>>, src: *mut raw::data_source) -> DataSource {
+    unsafe {
+      let name_ptr = raw::get_data_source_name(src);
+      let name = utils::cstr_to_string(name_ptr);
+      raw::wmem_free(std::ptr::null_mut(), name_ptr as *mut std::os::raw::c_void);
+
+      let tvb = raw::get_data_source_tvb(src);
+
+      DataSource {
+        name,
+        data: tvb_bytes(tvb, 0, usize::MAX),
+        prec: prec.clone(),
+        tvb,
+      }
+    }
+  }
+}
+
+fn raw_data_sources(prec: &Rc<RefCell<Pin<Box<Edt>>>>) -> Vec<*mut raw::data_source> {
+  let mut sources = Vec::new();
+
+  unsafe {
+    let mut item = prec.borrow().edt.pi.data_src;
+
+    while !item.is_null() {
+      sources.push((*item).data as *mut raw::data_source);
+
+      item = (*item).next;
+    }
+  }
+
+  sources
+}
+
+impl ParsedRec {
+  // The frame comes first.
+  pub fn data_sources(&self) -> Result<Vec<DataSource>, Error> {
+    if self.root_node.is_none() {
+      return Err(Error::NotDissected);
+    }
+
+    Ok(
+      raw_data_sources(&self.inner_pr)
+        .into_iter()
+        .map(|src| DataSource::new(&self.inner_pr, src))
+        .collect(),
+    )
+  }
+}
+
+impl ProtoNode {
+  pub fn get_data_source(&self) -> Option<DataSource> {
+    let range = self.byte_range()?;
+
+    raw_data_sources(&self.prec)
+      .into_iter()
+      .find(|src| unsafe { raw::get_data_source_tvb(*src) } == range.tvb)
+      .map(|src| DataSource::new(&self.prec, src))
+  }
+}
+
+impl ParsedRec {
+  // Like clicking in Wireshark's hex pane: the most specific visible field
+  // covering `offset` in the frame.
+  pub fn field_at(&self, offset: usize) -> Result<Option<ProtoNode>, Error> {
+    let sources = self.data_sources()?;
+
+    match sources.first() {
+      Some(frame) => self.field_at_in(frame, offset),
+      None => Ok(None),
+    }
+  }
+
+  pub fn field_at_in(
+    &self,
+    source: &DataSource,
+    offset: usize,
+  ) -> Result<Option<ProtoNode>, Error> {
+    let root = self.get_root_node()?;
+
+    // A source of another packet covers nothing here.
+    if !Rc::ptr_eq(&source.prec, &self.inner_pr) {
+      return Ok(None);
+    }
+
+    let mut best: Option<(ProtoNode, i16, usize)> = None;
+
+    for node in root.iter_depth_first() {
+      let Some(range) = node.byte_range() else {
+        continue;
+      };
+
+      if !source.contains(&range)
+        || range.length == 0
+        || node.is_hidden()
+        || offset < range.start
+        || offset >= range.start + range.length
+      {
+        continue;
+      }
+
+      let depth = node.get_depth();
+
+      let better = match best {
+        None => true,
+        Some((_, best_depth, best_length)) => {
+          depth > best_depth || (depth == best_depth && range.length < best_length)
+        }
+      };
+
+      if better {
+        best = Some((node, depth, range.length));
+      }
+    }
+
+    Ok(best.map(|(node, _, _)| node))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn dissect_all(session: &Session) -> Vec<ParsedRec> {
+    let mut wtap =
+      Wtap::new(concat!(env!("CARGO_MANIFEST_DIR"), "/data/1.pcap").to_string()).unwrap();
+    let mut precs = Vec::new();
+
+    while let Ok(mut rec) = wtap.read() {
+      let mut prec = session.new_prec(&mut rec);
+
+      prec.dissect();
+      precs.push(prec);
+    }
+
+    precs
+  }
+
+  #[test]
+  fn node_data_source() {
+    let _guard = utils::init_for_tests();
+
+    let session = Session::new();
+    let mut precs = dissect_all(&session);
+
+    let node = precs[0]
+      .get_root_node()
+      .unwrap()
+      .find_exhaustive("dns.qry.name".to_string())
+      .unwrap();
+    let source = node.get_data_source().unwrap();
+    let range = node.byte_range().unwrap();
+
+    assert_eq!(source.name, "Frame (90 bytes)");
+    assert_eq!(source.data.len(), 90);
+    assert!(source.contains(&range));
+
+    // Offsets of one packet's frame don't point into another's.
+    assert!(
+      precs[1]
+        .field_at_in(&source, range.start)
+        .unwrap()
+        .is_none()
+    );
+    assert_eq!(
+      precs[0]
+        .field_at_in(&source, range.start)
+        .unwrap()
+        .unwrap()
+        .get_abbrev(),
+      "dns.qry.name"
+    );
+
+    drop(node);
+    precs.remove(0);
+
+    assert_eq!(&source.data[range.start..range.start + 5], b"\x05users");
+  }
+}
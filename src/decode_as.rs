@@ -0,0 +1,255 @@
+use std::ffi::CString;
+use std::os::raw::c_void;
+
+use crate::Error;
+use crate::raw;
+use crate::utils;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeAsSelector {
+  Uint(u32),
+  String(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodeAsRule {
+  pub table: String,
+  pub selector: DecodeAsSelector,
+  // None when the entry was cleared ("(none)").
+  pub dissector: Option<String>,
+  pub initial: Option<String>,
+}
+
+// Dissector tables are global to libwireshark, so rules apply to every
+// session from the next dissected packet on.
+pub struct DecodeAs;
+
+impl DecodeAs {
+  // `dissector` is one of the table's handles, as shown in Wireshark's
+  // Decode As dialog ("HTTP"), by dissector name ("http-over-tcp") or by
+  // protocol ("http").
+  pub fn add(table: &str, selector: DecodeAsSelector, dissector: &str) -> Result<(), Error> {
+    let ctable = cstring(table)?;
+    let cdissector = cstring(dissector)?;
+
+    unsafe {
+      let dtable = find_table(table, &ctable)?;
+
+      let handle = find_handle(dtable, &ctable, dissector, &cdissector);
+
+      if handle.is_null() {
+        return Err(Error::DecodeAs(format!("no such dissector: {}", dissector)));
+      }
+
+      match check_selector(table, &ctable, selector)? {
+        DecodeAsSelector::Uint(pattern) => {
+          raw::dissector_change_uint(ctable.as_ptr(), pattern, handle);
+        }
+        DecodeAsSelector::String(pattern) => {
+          let cpattern = cstring(&pattern)?;
+
+          raw::dissector_change_string(ctable.as_ptr(), cpattern.as_ptr(), handle);
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  // Restores the entry the dissectors registered themselves.
+  pub fn remove(table: &str, selector: DecodeAsSelector) -> Result<(), Error> {
+    let ctable = cstring(table)?;
+
+    unsafe {
+      find_table(table, &ctable)?;
+
+      match check_selector(table, &ctable, selector)? {
+        DecodeAsSelector::Uint(pattern) => {
+          raw::dissector_reset_uint(ctable.as_ptr(), pattern);
+        }
+        DecodeAsSelector::String(pattern) => {
+          let cpattern = cstring(&pattern)?;
+
+          raw::dissector_reset_string(ctable.as_ptr(), cpattern.as_ptr());
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn rules() -> Vec<DecodeAsRule> {
+    let mut rules: Vec<DecodeAsRule> = Vec::new();
+
+    unsafe {
+      raw::dissector_all_tables_foreach_changed(
+        Some(collect_rule),
+        (&mut rules) as *mut Vec<DecodeAsRule> as *mut c_void,
+      );
+    }
+
+    rules
+  }
+
+  // Writes the active rules to decode_as_entries in the current profile.
+  pub fn save() -> Result<(), Error> {
+    let mut errmsg: *mut raw::gchar = std::ptr::null_mut();
+
+    unsafe {
+      if raw::save_decode_as_entries((&mut errmsg) as *mut *mut raw::gchar) < 0 {
+        let msg = utils::opt_cstr_to_string(errmsg)
+          .unwrap_or_else(|| "cannot save decode_as_entries".to_string());
+
+        if !errmsg.is_null() {
+          raw::g_free(errmsg as raw::gpointer);
+        }
+
+        return Err(Error::DecodeAs(msg));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+fn cstring(s: &str) -> Result<CString, Error> {
+  CString::new(s).map_err(|_| Error::DecodeAs(format!("invalid string: {}", s)))
+}
+
+unsafe fn find_table(table: &str, ctable: &CString) -> Result<raw::dissector_table_t, Error> {
+  let dtable = unsafe { raw::find_dissector_table(ctable.as_ptr()) };
+
+  if dtable.is_null() {
+    Err(Error::DecodeAs(format!(
+      "no such dissector table: {}",
+      table
+    )))
+  } else {
+    Ok(dtable)
+  }
+}
+
+// Only handles registered for the table are used: on tcp.port, "http" is
+// http-over-tcp, which does TCP reassembly, not the plain http handle.
+unsafe fn find_handle(
+  dtable: raw::dissector_table_t,
+  ctable: &CString,
+  dissector: &str,
+  cdissector: &CString,
+) -> raw::dissector_handle_t {
+  unsafe {
+    let handle = raw::dissector_table_get_dissector_handle(dtable, cdissector.as_ptr());
+
+    if !handle.is_null() {
+      return handle;
+    }
+
+    let mut handles: Vec<raw::dissector_handle_t> = Vec::new();
+
+    raw::dissector_table_foreach_handle(
+      ctable.as_ptr(),
+      Some(collect_handle),
+      (&mut handles) as *mut Vec<raw::dissector_handle_t> as raw::gpointer,
+    );
+
+    let handle = raw::find_dissector(cdissector.as_ptr());
+
+    if !handle.is_null() && handles.contains(&handle) {
+      return handle;
+    }
+
+    handles
+      .into_iter()
+      .find(|&handle| {
+        let proto_id = raw::dissector_handle_get_protocol_index(handle);
+
+        proto_id >= 0
+          && utils::opt_cstr_to_string(raw::proto_get_protocol_filter_name(proto_id)).as_deref()
+            == Some(dissector)
+      })
+      .unwrap_or(std::ptr::null_mut())
+  }
+}
+
+unsafe extern "C" fn collect_handle(data: raw::gpointer, user_data: raw::gpointer) {
+  unsafe {
+    let handles = &mut *(user_data as *mut Vec<raw::dissector_handle_t>);
+
+    handles.push(data as raw::dissector_handle_t);
+  }
+}
+
+fn is_uint_table(selector_type: raw::ftenum_t) -> bool {
+  matches!(
+    selector_type,
+    raw::ftenum_FT_UINT8 | raw::ftenum_FT_UINT16 | raw::ftenum_FT_UINT24 | raw::ftenum_FT_UINT32
+  )
+}
+
+fn is_string_table(selector_type: raw::ftenum_t) -> bool {
+  matches!(
+    selector_type,
+    raw::ftenum_FT_STRING
+      | raw::ftenum_FT_STRINGZ
+      | raw::ftenum_FT_UINT_STRING
+      | raw::ftenum_FT_STRINGZPAD
+      | raw::ftenum_FT_STRINGZTRUNC
+  )
+}
+
+unsafe fn check_selector(
+  table: &str,
+  ctable: &CString,
+  selector: DecodeAsSelector,
+) -> Result<DecodeAsSelector, Error> {
+  let selector_type = unsafe { raw::get_dissector_table_selector_type(ctable.as_ptr()) };
+
+  match selector {
+    DecodeAsSelector::Uint(_) if is_uint_table(selector_type) => Ok(selector),
+    DecodeAsSelector::String(_) if is_string_table(selector_type) => Ok(selector),
+    _ => Err(Error::DecodeAs(format!(
+      "{}: selector does not match the table type",
+      table
+    ))),
+  }
+}
+
+unsafe fn handle_name(handle: raw::dissector_handle_t) -> Option<String> {
+  if handle.is_null() {
+    return None;
+  }
+
+  unsafe {
+    utils::opt_cstr_to_string(raw::dissector_handle_get_dissector_name(handle))
+      .or_else(|| utils::opt_cstr_to_string(raw::dissector_handle_get_description(handle)))
+  }
+}
+
+unsafe extern "C" fn collect_rule(
+  table_name: *const raw::gchar,
+  selector_type: raw::ftenum_t,
+  key: raw::gpointer,
+  value: raw::gpointer,
+  user_data: raw::gpointer,
+) {
+  unsafe {
+    let rules = &mut *(user_data as *mut Vec<DecodeAsRule>);
+
+    let selector = if is_uint_table(selector_type) {
+      DecodeAsSelector::Uint(key as usize as u32)
+    } else if is_string_table(selector_type) {
+      DecodeAsSelector::String(utils::cstr_to_string(key as *const raw::gchar))
+    } else {
+      return;
+    };
+
+    let entry = value as *mut raw::dtbl_entry_t;
+
+    rules.push(DecodeAsRule {
+      table: utils::cstr_to_string(table_name),
+      selector,
+      dissector: handle_name(raw::dtbl_entry_get_handle(entry)),
+      initial: handle_name(raw::dtbl_entry_get_initial_handle(entry)),
+    });
+  }
+}
@@ -0,0 +1,588 @@
+use std::cell::Cell;
+use std::os::raw::c_void;
+use std::path::{Path, PathBuf};
+
+use crate::utils;
+use crate::*;
+
+#[derive(Debug, Clone)]
+pub struct TlsRsaKey {
+  // IP address of the server, or "any".
+  pub address: String,
+  // TCP port, or "start_tls", or "0" for any port.
+  pub port: String,
+  // Dissector for the decrypted payload, e.g. "http".
+  pub protocol: String,
+  pub key_file: PathBuf,
+  // Only used for PKCS#12 key files.
+  pub password: Option<String>,
+}
+
+impl TlsRsaKey {
+  pub fn new(address: String, port: u16, protocol: String, key_file: PathBuf) -> TlsRsaKey {
+    TlsRsaKey {
+      address,
+      port: port.to_string(),
+      protocol,
+      key_file,
+      password: None,
+    }
+  }
+}
+
+thread_local! {
+  // Bumped whenever epan resets the dissectors' key stores, which creating
+  // or dropping any session does.
+  static KEY_STORES: Cell<u64> = const { Cell::new(0) };
+}
+
+pub(crate) fn key_stores_reset() {
+  KEY_STORES.set(KEY_STORES.get() + 1);
+}
+
+fn feed_secrets(secrets_type: u32, secrets: &[u8]) {
+  unsafe {
+    raw::secrets_wtap_callback(
+      secrets_type,
+      secrets.as_ptr() as *const c_void,
+      secrets.len() as u32,
+    );
+  }
+}
+
+// Secrets added to a session in memory, given to the dissectors' key stores
+// before it dissects whenever they have been reset since.
+#[derive(Debug, Default)]
+pub(crate) struct SessionSecrets {
+  secrets: Vec<(u32, Vec<u8>)>,
+  loaded: Option<u64>,
+}
+
+impl SessionSecrets {
+  fn add(&mut self, secrets_type: u32, secrets: Vec<u8>) {
+    if self.loaded == Some(KEY_STORES.get()) {
+      feed_secrets(secrets_type, &secrets);
+    }
+
+    self.secrets.push((secrets_type, secrets));
+  }
+
+  pub(crate) fn load(&mut self) {
+    let current = KEY_STORES.get();
+
+    if self.loaded == Some(current) {
+      return;
+    }
+
+    for (secrets_type, secrets) in self.secrets.iter() {
+      feed_secrets(*secrets_type, secrets);
+    }
+
+    self.loaded = Some(current);
+  }
+}
+
+// Secrets held in memory belong to the session they are added to. The key
+// stores themselves are global to libwireshark though, so keys already
+// given to them stay usable by other live sessions, and after
+// clear_secrets(), until the stores are next reset.
+impl Session {
+  // Takes key log lines in SSLKEYLOGFILE format, the same as a pcapng
+  // Decryption Secrets Block would carry. Used for both TLS and QUIC.
+  pub fn add_tls_keylog(&self, keylog: &[u8]) {
+    self
+      .epan
+      .borrow_mut()
+      .secrets
+      .add(raw::SECRETS_TYPE_TLS, keylog.to_vec());
+  }
+
+  pub fn add_wireguard_key(&self, key: &WireGuardKey) {
+    let line = match key {
+      WireGuardKey::LocalStaticPrivate(key) => format!("LOCAL_STATIC_PRIVATE_KEY = {}\n", key),
+      WireGuardKey::RemoteStaticPublic(key) => format!("REMOTE_STATIC_PUBLIC_KEY = {}\n", key),
+      WireGuardKey::LocalEphemeralPrivate(key) => {
+        format!("LOCAL_EPHEMERAL_PRIVATE_KEY = {}\n", key)
+      }
+      WireGuardKey::Preshared(key) => format!("PRESHARED_KEY = {}\n", key),
+    };
+
+    self
+      .epan
+      .borrow_mut()
+      .secrets
+      .add(raw::SECRETS_TYPE_WIREGUARD, line.into_bytes());
+  }
+
+  pub fn clear_secrets(&self) {
+    self.epan.borrow_mut().secrets.secrets.clear();
+  }
+}
+
+// Key files and tables are preferences, so like other preferences and
+// Decode As rules they apply to every session.
+pub struct Decryption;
+
+impl Decryption {
+  // Reads an SSLKEYLOGFILE, used for both TLS and QUIC.
+  pub fn set_tls_keylog_file(path: &Path) -> Result<(), Error> {
+    Prefs::set("tls.keylog_file", &path.to_string_lossy())?;
+    Prefs::apply();
+
+    Ok(())
+  }
+
+  pub fn add_tls_rsa_key(key: &TlsRsaKey) -> Result<(), Error> {
+    let key_file = key.key_file.to_string_lossy();
+
+    let record = utils::uat_record(&[
+      &key.address,
+      &key.port,
+      &key.protocol,
+      &key_file,
+      key.password.as_deref().unwrap_or(""),
+    ]);
+
+    Prefs::set("uat", &format!("ssl_keys:{}", record))?;
+    Prefs::apply();
+
+    Ok(())
+  }
+}
+
+// The passphrase and SSID of a wpa-pwd key are separated by a colon and
+// URI-unescaped, so both need ':' and '%' escaped.
+fn uri_escape(s: &str) -> String {
+  s.replace('%', "%25").replace(':', "%3A")
+}
+
+#[derive(Debug, Clone)]
+pub enum Ieee80211Key {
+  // Hex encoded key.
+  Wep(String),
+  Passphrase {
+    passphrase: String,
+    ssid: Option<String>,
+  },
+  // Hex encoded 256 bit pre-shared key.
+  Psk(String),
+  Tk(String),
+  Msk(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EspEncryption {
+  Null,
+  TripleDesCbc,
+  AesCbc,
+  AesCtr,
+  DesCbc,
+  Cast5Cbc,
+  BlowfishCbc,
+  TwofishCbc,
+  AesGcm8,
+  AesGcm12,
+  AesGcm16,
+  NullAesGmac,
+  ChaCha20Poly1305,
+}
+
+impl EspEncryption {
+  // As listed in the esp_sa table.
+  fn as_str(&self) -> &'static str {
+    match self {
+      EspEncryption::Null => "NULL",
+      EspEncryption::TripleDesCbc => "TripleDES-CBC [RFC2451]",
+      EspEncryption::AesCbc => "AES-CBC [RFC3602]",
+      EspEncryption::AesCtr => "AES-CTR [RFC3686]",
+      EspEncryption::DesCbc => "DES-CBC [RFC2405]",
+      EspEncryption::Cast5Cbc => "CAST5-CBC [RFC2144]",
+      EspEncryption::BlowfishCbc => "BLOWFISH-CBC [RFC2451]",
+      EspEncryption::TwofishCbc => "TWOFISH-CBC",
+      EspEncryption::AesGcm8 => "AES-GCM with 8 octet ICV [RFC4106]",
+      EspEncryption::AesGcm12 => "AES-GCM with 12 octet ICV [RFC4106]",
+      EspEncryption::AesGcm16 => "AES-GCM with 16 octet ICV [RFC4106]",
+      EspEncryption::NullAesGmac => "NULL Encryption with AES-GMAC Authentication [RFC4543]",
+      EspEncryption::ChaCha20Poly1305 => "ChaCha20 with Poly1305 [RFC7634]",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EspAuthentication {
+  Null,
+  HmacSha1_96,
+  HmacSha256_96,
+  HmacSha256_128,
+  HmacSha384_192,
+  HmacSha512_256,
+  HmacMd5_96,
+  MacRipemd160_96,
+  Any64,
+  Any96,
+  Any128,
+  Any192,
+  Any256,
+}
+
+impl EspAuthentication {
+  fn as_str(&self) -> &'static str {
+    match self {
+      EspAuthentication::Null => "NULL",
+      EspAuthentication::HmacSha1_96 => "HMAC-SHA-1-96 [RFC2404]",
+      EspAuthentication::HmacSha256_96 => "HMAC-SHA-256-96 [draft-ietf-ipsec-ciph-sha-256-00]",
+      EspAuthentication::HmacSha256_128 => "HMAC-SHA-256-128 [RFC4868]",
+      EspAuthentication::HmacSha384_192 => "HMAC-SHA-384-192 [RFC4868]",
+      EspAuthentication::HmacSha512_256 => "HMAC-SHA-512-256 [RFC4868]",
+      EspAuthentication::HmacMd5_96 => "HMAC-MD5-96 [RFC2403]",
+      EspAuthentication::MacRipemd160_96 => "MAC-RIPEMD-160-96 [RFC2857]",
+      EspAuthentication::Any64 => "ANY 64 bit authentication [no checking]",
+      EspAuthentication::Any96 => "ANY 96 bit authentication [no checking]",
+      EspAuthentication::Any128 => "ANY 128 bit authentication [no checking]",
+      EspAuthentication::Any192 => "ANY 192 bit authentication [no checking]",
+      EspAuthentication::Any256 => "ANY 256 bit authentication [no checking]",
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct EspSa {
+  pub ipv6: bool,
+  // Addresses and SPI accept "*" as a wildcard.
+  pub src: String,
+  pub dst: String,
+  pub spi: String,
+  pub encryption: EspEncryption,
+  // Hex encoded with a 0x prefix.
+  pub encryption_key: String,
+  pub authentication: EspAuthentication,
+  pub authentication_key: String,
+  // The upper 32 bits of the sequence number, for SAs that use Extended
+  // Sequence Numbers.
+  pub esn: Option<u32>,
+}
+
+// Base64 encoded Curve25519 keys, as written by the WireGuard key log
+// tools.
+#[derive(Debug, Clone)]
+pub enum WireGuardKey {
+  LocalStaticPrivate(String),
+  RemoteStaticPublic(String),
+  LocalEphemeralPrivate(String),
+  Preshared(String),
+}
+
+impl Decryption {
+  pub fn add_ieee80211_key(key: &Ieee80211Key) -> Result<(), Error> {
+    let (key_type, value) = match key {
+      Ieee80211Key::Wep(key) => ("wep", key.clone()),
+      Ieee80211Key::Passphrase { passphrase, ssid } => match ssid {
+        Some(ssid) => (
+          "wpa-pwd",
+          format!("{}:{}", uri_escape(passphrase), uri_escape(ssid)),
+        ),
+        None => ("wpa-pwd", uri_escape(passphrase)),
+      },
+      Ieee80211Key::Psk(key) => ("wpa-psk", key.clone()),
+      Ieee80211Key::Tk(key) => ("tk", key.clone()),
+      Ieee80211Key::Msk(key) => ("msk", key.clone()),
+    };
+
+    Prefs::set("wlan.enable_decryption", "TRUE")?;
+    Prefs::set(
+      "uat",
+      &format!("80211_keys:{}", utils::uat_record(&[key_type, &value])),
+    )?;
+    Prefs::apply();
+
+    Ok(())
+  }
+
+  // The esp_sa table of Wireshark 4.x, whose last two columns give the
+  // sequence number length and the ESN high bits.
+  pub fn add_esp_sa(sa: &EspSa) -> Result<(), Error> {
+    let esn_high = sa.esn.unwrap_or(0).to_string();
+
+    let record = utils::uat_record(&[
+      if sa.ipv6 { "IPv6" } else { "IPv4" },
+      &sa.src,
+      &sa.dst,
+      &sa.spi,
+      sa.encryption.as_str(),
+      &sa.encryption_key,
+      sa.authentication.as_str(),
+      &sa.authentication_key,
+      if sa.esn.is_some() { "64-bit" } else { "32-bit" },
+      &esn_high,
+    ]);
+
+    Prefs::set("esp.enable_encryption_decode", "TRUE")?;
+    Prefs::set("uat", &format!("esp_sa:{}", record))?;
+    Prefs::apply();
+
+    Ok(())
+  }
+
+  pub fn set_ssh_keylog_file(path: &Path) -> Result<(), Error> {
+    Prefs::set("ssh.keylog_file", &path.to_string_lossy())?;
+    Prefs::apply();
+
+    Ok(())
+  }
+
+  pub fn set_kerberos_keytab(path: &Path) -> Result<(), Error> {
+    Prefs::set("kerberos.decrypt", "TRUE")?;
+    Prefs::set("kerberos.file", &path.to_string_lossy())?;
+    Prefs::apply();
+
+    Ok(())
+  }
+
+  pub fn set_wireguard_keylog_file(path: &Path) -> Result<(), Error> {
+    Prefs::set("wg.keylog_file", &path.to_string_lossy())?;
+    Prefs::apply();
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn data(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+      .join("data")
+      .join(name)
+  }
+
+  fn dissect_file(session: &Session, name: &str, mut f: impl FnMut(&ParsedRec)) {
+    let mut wtap = Wtap::new(data(name).to_string_lossy().into_owned()).unwrap();
+    let ctx = session.new_capture_context().unwrap();
+
+    loop {
+      let mut rec = match wtap.read() {
+        Ok(rec) => rec,
+        Err(Error::EOF) => break,
+        Err(e) => panic!("{:?}", e),
+      };
+
+      let mut prec = ctx.new_prec(&mut rec);
+
+      prec.dissect();
+
+      f(&prec);
+    }
+  }
+
+  fn find(prec: &ParsedRec, abbrev: &str) -> Option<ProtoNode> {
+    prec
+      .get_root_node()
+      .unwrap()
+      .find_hierarchical(abbrev.to_string())
+  }
+
+  // Request URIs of data/tls.pcap, a TLS 1.3 HTTP/1.1 exchange whose
+  // secrets are in data/tls.keylog.
+  fn http_requests(session: &Session) -> Vec<String> {
+    let mut uris = Vec::new();
+
+    dissect_file(session, "tls.pcap", |prec| {
+      uris.extend(
+        find(prec, "http.request.uri")
+          .and_then(|node| node.get_fvalue())
+          .and_then(|fv| fv.get_string()),
+      );
+    });
+
+    uris
+  }
+
+  // Numbers of the frames of a capture that have the field.
+  fn frames_with(session: &Session, name: &str, abbrev: &str) -> Vec<u32> {
+    let mut frames = Vec::new();
+
+    dissect_file(session, name, |prec| {
+      if find(prec, abbrev).is_some() {
+        frames.push(prec.get_frame_number());
+      }
+    });
+
+    frames
+  }
+
+  #[test]
+  fn decrypt_tls() {
+    let _guard = utils::init_for_tests();
+
+    assert!(http_requests(&Session::new()).is_empty());
+
+    Decryption::set_tls_keylog_file(&data("tls.keylog")).unwrap();
+
+    assert_eq!(http_requests(&Session::new()), vec!["/index.html"]);
+
+    reset_pref("tls.keylog_file", "");
+
+    assert!(http_requests(&Session::new()).is_empty());
+
+    let keylog = std::fs::read(data("tls.keylog")).unwrap();
+    let session = Session::new();
+
+    session.add_tls_keylog(&keylog);
+
+    // Other sessions reset the key stores without losing this session's
+    // keys, and don't get them.
+    drop(Session::new());
+
+    assert!(http_requests(&Session::new()).is_empty());
+    assert_eq!(http_requests(&session), vec!["/index.html"]);
+
+    let cleared = Session::new();
+
+    cleared.add_tls_keylog(&keylog);
+    cleared.clear_secrets();
+    drop(Session::new());
+
+    assert!(http_requests(&cleared).is_empty());
+  }
+
+  // data/quic.pcap holds a client and a server Initial, then a 1-RTT packet
+  // carrying a STREAM frame, encrypted with the client traffic secret in
+  // data/quic.keylog.
+  #[test]
+  fn decrypt_quic() {
+    let _guard = utils::init_for_tests();
+
+    assert!(frames_with(&Session::new(), "quic.pcap", "quic.stream.stream_id").is_empty());
+
+    let session = Session::new();
+
+    session.add_tls_keylog(&std::fs::read(data("quic.keylog")).unwrap());
+
+    assert_eq!(
+      frames_with(&session, "quic.pcap", "quic.stream.stream_id"),
+      vec![3]
+    );
+
+    Decryption::set_tls_keylog_file(&data("quic.keylog")).unwrap();
+
+    let with_file = frames_with(&Session::new(), "quic.pcap", "quic.stream.stream_id");
+
+    reset_pref("tls.keylog_file", "");
+
+    assert_eq!(with_file, vec![3]);
+  }
+
+  fn reset_pref(name: &str, value: &str) {
+    Prefs::set(name, value).unwrap();
+    Prefs::apply();
+  }
+
+  #[test]
+  fn escape_passphrase() {
+    assert_eq!(uri_escape("pass:word%"), "pass%3Aword%25");
+    assert_eq!(uri_escape("plain"), "plain");
+  }
+
+  // data/wep.pcap is a WEP protected 802.11 data frame carrying UDP.
+  #[test]
+  fn decrypt_ieee80211() {
+    let _guard = utils::init_for_tests();
+
+    assert!(frames_with(&Session::new(), "wep.pcap", "udp").is_empty());
+
+    Decryption::add_ieee80211_key(&Ieee80211Key::Wep("0102030405".to_string())).unwrap();
+
+    assert_eq!(frames_with(&Session::new(), "wep.pcap", "udp"), vec![1]);
+  }
+
+  // data/esp.pcap is an ESP packet in transport mode carrying UDP.
+  #[test]
+  fn decrypt_esp() {
+    let _guard = utils::init_for_tests();
+
+    assert!(frames_with(&Session::new(), "esp.pcap", "udp").is_empty());
+
+    Decryption::add_esp_sa(&EspSa {
+      ipv6: false,
+      src: "10.0.0.1".to_string(),
+      dst: "10.0.0.2".to_string(),
+      spi: "0x00001000".to_string(),
+      encryption: EspEncryption::AesCbc,
+      encryption_key: "0x000102030405060708090a0b0c0d0e0f".to_string(),
+      authentication: EspAuthentication::HmacSha1_96,
+      authentication_key: "0x202122232425262728292a2b2c2d2e2f30313233".to_string(),
+      esn: None,
+    })
+    .unwrap();
+
+    let frames = frames_with(&Session::new(), "esp.pcap", "udp");
+
+    reset_pref("esp.enable_encryption_decode", "FALSE");
+
+    assert_eq!(frames, vec![1]);
+  }
+
+  // data/ssh.pcap is a curve25519-sha256 key exchange followed by an
+  // encrypted service request and accept. data/ssh.keylog holds the
+  // client's ephemeral key.
+  #[test]
+  fn decrypt_ssh() {
+    let _guard = utils::init_for_tests();
+
+    assert!(frames_with(&Session::new(), "ssh.pcap", "ssh.service_name").is_empty());
+
+    Decryption::set_ssh_keylog_file(&data("ssh.keylog")).unwrap();
+
+    let frames = frames_with(&Session::new(), "ssh.pcap", "ssh.service_name");
+
+    reset_pref("ssh.keylog_file", "");
+
+    assert_eq!(frames, vec![12, 13]);
+  }
+
+  // data/kerberos.pcap is an AS-REQ whose PA-ENC-TIMESTAMP is encrypted with
+  // the RC4-HMAC key in data/kerberos.keytab.
+  #[test]
+  fn decrypt_kerberos() {
+    let _guard = utils::init_for_tests();
+
+    assert!(frames_with(&Session::new(), "kerberos.pcap", "kerberos.patimestamp").is_empty());
+
+    Decryption::set_kerberos_keytab(&data("kerberos.keytab")).unwrap();
+
+    let frames = frames_with(&Session::new(), "kerberos.pcap", "kerberos.patimestamp");
+
+    reset_pref("kerberos.decrypt", "FALSE");
+
+    assert_eq!(frames, vec![1]);
+  }
+
+  // data/wireguard.pcap is a handshake initiation; data/wireguard.keylog
+  // holds the responder's static private key.
+  #[test]
+  fn decrypt_wireguard() {
+    let _guard = utils::init_for_tests();
+
+    assert!(frames_with(&Session::new(), "wireguard.pcap", "wg.timestamp.value").is_empty());
+
+    let session = Session::new();
+
+    session.add_wireguard_key(&WireGuardKey::LocalStaticPrivate(
+      "IiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIiIlI=".to_string(),
+    ));
+
+    assert_eq!(
+      frames_with(&session, "wireguard.pcap", "wg.timestamp.value"),
+      vec![1]
+    );
+
+    Decryption::set_wireguard_keylog_file(&data("wireguard.keylog")).unwrap();
+
+    let frames = frames_with(&Session::new(), "wireguard.pcap", "wg.timestamp.value");
+
+    reset_pref("wg.keylog_file", "");
+
+    assert_eq!(frames, vec![1]);
+  }
+}
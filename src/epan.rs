@@ -1,6 +1,10 @@
 use std::pin::Pin;
 use std::{cell::RefCell, rc::Rc};
 
+use crate::context::InnerCaptureContext;
+use crate::decrypt::SessionSecrets;
+use crate::provider::ProviderData;
+use crate::utils;
 use crate::wtap::*;
 
 use crate::*;
@@ -28,6 +32,14 @@ pub struct ParsedRec {
   pub(crate) root_node: Option<ProtoNode>,
   pub(crate) offset: raw::gint64,
   pub(crate) file_type: i32,
+  pub(crate) ctx: Option<Rc<RefCell<InnerCaptureContext>>>,
+  pub(crate) frame_num: u32,
+  // The frame_data's length, which is also set for non-packet records.
+  pub(crate) pkt_len: u32,
+  pub(crate) columns: Option<Vec<String>>,
+  // The expert tap's error message when it could not be registered.
+  pub(crate) expert_infos: Option<Result<Vec<ExpertInfo>, String>>,
+  pub(crate) color_rule: Option<Option<ColorRule>>,
 }
 
 impl Drop for Edt {
@@ -39,7 +51,11 @@ impl Drop for Edt {
 }
 
 impl ParsedRec {
-  fn new(session: Rc<RefCell<InnerEpanSession>>, rec: &mut WtapRec) -> ParsedRec {
+  pub(crate) fn new(
+    session: Rc<RefCell<InnerEpanSession>>,
+    rec: &mut WtapRec,
+    ctx: Option<Rc<RefCell<InnerCaptureContext>>>,
+  ) -> ParsedRec {
     unsafe {
       let prec = ParsedRec {
         session: session.clone(),
@@ -48,6 +64,12 @@ impl ParsedRec {
         root_node: None,
         offset: rec.offset,
         file_type: rec.file_type,
+        ctx,
+        frame_num: 0,
+        pkt_len: 0,
+        columns: None,
+        expert_infos: None,
+        color_rule: None,
       };
 
       raw::epan_dissect_init(
@@ -83,39 +105,145 @@ impl ParsedRec {
     Ok(root.get_children())
   }
 
+  pub fn get_frame_number(&self) -> u32 {
+    self.frame_num
+  }
+
+  // Empty when the session has no columns set.
+  pub fn columns(&self) -> Result<Vec<String>, Error> {
+    match self.columns {
+      None => Err(Error::NotDissected),
+      Some(ref columns) => Ok(columns.clone()),
+    }
+  }
+
   pub fn dissect(&mut self) -> () {
     let raw_rec = &mut self.rec.borrow_mut().rec;
 
+    self.session.borrow_mut().secrets.load();
+
+    let cinfo = match self.session.borrow_mut().cinfo {
+      Some(ref mut cinfo) => cinfo.as_mut() as *mut raw::column_info,
+      None => std::ptr::null_mut(),
+    };
+
+    let collector = expert::ExpertCollector::register();
+
     self.root_node = unsafe {
       let mut inner_pr = self.inner_pr.borrow_mut();
-      let mut fdata: raw::frame_data = std::mem::zeroed();
-
-      raw::frame_data_init(
-        (&mut fdata) as *mut raw::frame_data,
-        1,
-        raw_rec as *mut raw::wtap_rec,
-        self.offset,
-        0,
-      );
 
-      raw::epan_dissect_run(
+      if !cinfo.is_null() {
+        raw::col_custom_prime_edt((&mut inner_pr.edt) as *mut raw::epan_dissect_t, cinfo);
+      }
+
+      color::prime_edt((&mut inner_pr.edt) as *mut raw::epan_dissect_t);
+
+      match self.ctx {
+        Some(ref ctx) => {
+          // Dissecting the same record again revisits its frame rather than
+          // adding a new one.
+          let existing = ctx.borrow_mut().get_frame(self.frame_num);
+
+          let provider = self.session.borrow().provider.as_ref() as *const ProviderData;
+
+          let fdata = match existing {
+            Some(fdata) => fdata,
+            None => {
+              let fdata = ctx
+                .borrow_mut()
+                .new_frame(raw_rec as *mut raw::wtap_rec, self.offset);
+
+              if (*provider).load_modified_block((*fdata).num) {
+                (*fdata).set_has_modified_block(1);
+              }
+
+              fdata
+            }
+          };
+
+          (*provider).with_context(ctx, || {
+            raw::epan_dissect_run_with_taps(
+              (&mut inner_pr.edt) as *mut raw::epan_dissect_t,
+              self.file_type,
+              raw_rec as *mut raw::wtap_rec,
+              fdata,
+              cinfo,
+            )
+          });
+
+          if !cinfo.is_null() {
+            raw::epan_dissect_fill_in_columns(
+              (&mut inner_pr.edt) as *mut raw::epan_dissect_t,
+              false,
+              true,
+            );
+          }
+
+          if existing.is_none() {
+            ctx.borrow_mut().finish_frame(fdata);
+          }
+
+          self.frame_num = (*fdata).num;
+          self.pkt_len = (*fdata).pkt_len;
+        }
+        None => {
+          let mut fdata: raw::frame_data = std::mem::zeroed();
+
+          raw::frame_data_init(
+            (&mut fdata) as *mut raw::frame_data,
+            1,
+            raw_rec as *mut raw::wtap_rec,
+            self.offset,
+            0,
+          );
+
+          raw::epan_dissect_run_with_taps(
+            (&mut inner_pr.edt) as *mut raw::epan_dissect_t,
+            self.file_type,
+            raw_rec as *mut raw::wtap_rec,
+            (&mut fdata) as *mut raw::frame_data,
+            cinfo,
+          );
+
+          if !cinfo.is_null() {
+            raw::epan_dissect_fill_in_columns(
+              (&mut inner_pr.edt) as *mut raw::epan_dissect_t,
+              false,
+              true,
+            );
+          }
+
+          self.frame_num = 1;
+          self.pkt_len = fdata.pkt_len;
+
+          raw::frame_data_destroy((&mut fdata) as *mut raw::frame_data);
+        }
+      }
+
+      self.color_rule = Some(color::colorize(
         (&mut inner_pr.edt) as *mut raw::epan_dissect_t,
-        self.file_type,
-        raw_rec as *mut raw::wtap_rec,
-        (&mut fdata) as *mut raw::frame_data,
-        std::ptr::null_mut(),
-      );
+      ));
 
-      raw::frame_data_destroy((&mut fdata) as *mut raw::frame_data);
+      self.columns = Some(if cinfo.is_null() {
+        Vec::new()
+      } else {
+        column::column_values(cinfo)
+      });
 
       Some(ProtoNode::new(self.inner_pr.clone(), inner_pr.edt.tree, 0))
     };
+
+    self.expert_infos = Some(collector.map(|collector| collector.finish(&self.inner_pr)));
   }
 }
 
 #[derive(Debug)]
 pub struct InnerEpanSession {
   pub(crate) epan: *mut raw::epan_session,
+  pub(crate) provider: Box<ProviderData>,
+  pub(crate) cinfo: Option<Box<raw::column_info>>,
+  pub(crate) has_context: bool,
+  pub(crate) secrets: SessionSecrets,
 }
 
 pub struct Session {
@@ -125,37 +253,86 @@ pub struct Session {
 impl Drop for InnerEpanSession {
   fn drop(&mut self) {
     unsafe {
+      if let Some(ref mut cinfo) = self.cinfo {
+        raw::col_cleanup(cinfo.as_mut() as *mut raw::column_info);
+      }
+
       raw::epan_free(self.epan);
     }
+
+    decrypt::key_stores_reset();
   }
 }
 
 impl Session {
   pub fn new() -> Session {
+    Session::create(None)
+  }
+
+  pub fn with_provider(provider: Box<dyn PacketProvider>) -> Session {
+    Session::create(Some(provider))
+  }
+
+  fn create(provider: Option<Box<dyn PacketProvider>>) -> Session {
+    let provider = Box::new(ProviderData::new(provider));
+
     let epan = unsafe {
-      let funcs = raw::packet_provider_funcs {
-        get_frame_ts: None,
-        get_interface_name: None,
-        get_interface_description: None,
-        get_modified_block: None,
-        get_process_id: None,
-        get_process_name: None,
-        get_process_uuid: None,
-        get_start_ts: None,
-      };
+      let funcs = ProviderData::funcs();
 
       raw::epan_new(
-        std::ptr::null_mut(),
+        provider.as_ref() as *const ProviderData as *mut raw::packet_provider_data,
         (&funcs) as *const raw::packet_provider_funcs,
       )
     };
 
+    decrypt::key_stores_reset();
+
     Session {
-      epan: Rc::new(RefCell::new(InnerEpanSession { epan })),
+      epan: Rc::new(RefCell::new(InnerEpanSession {
+        epan,
+        provider,
+        cinfo: None,
+        has_context: false,
+        secrets: SessionSecrets::default(),
+      })),
     }
   }
 
   pub fn new_prec(&self, rec: &mut WtapRec) -> ParsedRec {
-    ParsedRec::new(self.epan.clone(), rec)
+    ParsedRec::new(self.epan.clone(), rec, None)
+  }
+
+  pub fn new_capture_context(&self) -> Result<CaptureContext, Error> {
+    CaptureContext::new(self)
+  }
+
+  // Records dissected afterwards fill in these columns, for this session
+  // only. Frame numbers, relative times and deltas count from the first
+  // record of a CaptureContext; without one every record is frame 1.
+  pub fn set_columns(&self, columns: &[Column]) -> Result<(), Error> {
+    let cinfo = column::build_columns(columns)?;
+
+    let mut inner = self.epan.borrow_mut();
+
+    if let Some(ref mut old) = inner.cinfo {
+      unsafe {
+        raw::col_cleanup(old.as_mut() as *mut raw::column_info);
+      }
+    }
+
+    inner.cinfo = Some(cinfo);
+
+    Ok(())
+  }
+
+  pub fn get_column_titles(&self) -> Vec<String> {
+    match self.epan.borrow().cinfo {
+      Some(ref cinfo) => unsafe {
+        (0..cinfo.num_cols as usize)
+          .map(|i| utils::opt_cstr_to_string((*cinfo.columns.add(i)).col_title).unwrap_or_default())
+          .collect()
+      },
+      None => Vec::new(),
+    }
   }
 }
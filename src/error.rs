@@ -27,6 +27,26 @@ impl Display for WsError {
 pub enum Error {
   EOF,
   NotDissected,
+  NoColumns,
   InvalidFilter(String),
+  InvalidCaptureFilter(String),
+  InvalidField(String),
+  InvalidColumn(String),
+  InvalidInterval,
+  ContextExists,
+  CaptureError(String),
+  Extcap(String),
+  IoError(std::io::Error),
+  InvalidPreference(String),
+  Profile(String),
+  DecodeAs(String),
+  Protocol(String),
+  Tap(String),
   WsError(WsError),
 }
+
+impl From<std::io::Error> for Error {
+  fn from(err: std::io::Error) -> Self {
+    Error::IoError(err)
+  }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::raw::c_void;
+use std::pin::Pin;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::utils;
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExpertSeverity {
+  Comment,
+  Chat,
+  Note,
+  Warning,
+  Error,
+}
+
+impl ExpertSeverity {
+  fn from_raw(severity: u32) -> Option<ExpertSeverity> {
+    match severity {
+      raw::PI_COMMENT => Some(ExpertSeverity::Comment),
+      raw::PI_CHAT => Some(ExpertSeverity::Chat),
+      raw::PI_NOTE => Some(ExpertSeverity::Note),
+      raw::PI_WARN => Some(ExpertSeverity::Warning),
+      raw::PI_ERROR => Some(ExpertSeverity::Error),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExpertGroup {
+  Checksum,
+  Sequence,
+  ResponseCode,
+  RequestCode,
+  Undecoded,
+  Reassemble,
+  Malformed,
+  Debug,
+  Protocol,
+  Security,
+  Comments,
+  Decryption,
+  Assumption,
+  Deprecated,
+  Receive,
+  Interface,
+  Other(u32),
+}
+
+impl ExpertGroup {
+  fn from_raw(group: u32) -> ExpertGroup {
+    match group {
+      raw::PI_CHECKSUM => ExpertGroup::Checksum,
+      raw::PI_SEQUENCE => ExpertGroup::Sequence,
+      raw::PI_RESPONSE_CODE => ExpertGroup::ResponseCode,
+      raw::PI_REQUEST_CODE => ExpertGroup::RequestCode,
+      raw::PI_UNDECODED => ExpertGroup::Undecoded,
+      raw::PI_REASSEMBLE => ExpertGroup::Reassemble,
+      raw::PI_MALFORMED => ExpertGroup::Malformed,
+      raw::PI_DEBUG => ExpertGroup::Debug,
+      raw::PI_PROTOCOL => ExpertGroup::Protocol,
+      raw::PI_SECURITY => ExpertGroup::Security,
+      raw::PI_COMMENTS_GROUP => ExpertGroup::Comments,
+      raw::PI_DECRYPTION => ExpertGroup::Decryption,
+      raw::PI_ASSUMPTION => ExpertGroup::Assumption,
+      raw::PI_DEPRECATED => ExpertGroup::Deprecated,
+      raw::PI_RECEIVE => ExpertGroup::Receive,
+      raw::PI_INTERFACE => ExpertGroup::Interface,
+      other => ExpertGroup::Other(other),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExpertInfo {
+  pub severity: ExpertSeverity,
+  pub group: ExpertGroup,
+  // The protocol being dissected when the item was added, e.g. "TCP".
+  pub protocol: Option<String>,
+  // The expert info field, e.g. "tcp.analysis.retransmission", or
+  // "_ws.expert.message" for items without one.
+  pub abbrev: String,
+  pub message: String,
+  // The field the item is attached to, None for items added without one.
+  pub node: Option<ProtoNode>,
+}
+
+impl ExpertInfo {
+  pub fn is_malformed(&self) -> bool {
+    self.group == ExpertGroup::Malformed
+  }
+
+  // A dissector threw an exception other than running past the end of the
+  // packet.
+  pub fn is_dissector_bug(&self) -> bool {
+    self.abbrev == "_ws.malformed.dissector_bug"
+  }
+}
+
+// What the expert tap queued for one item, read before the packet's memory
+// is released.
+struct TappedExpertInfo {
+  severity: u32,
+  group: u32,
+  protocol: Option<String>,
+  abbrev: String,
+  message: String,
+  pitem: *mut raw::proto_node,
+}
+
+// Listens on the expert tap for the dissection of one record. Expert items
+// are collected from the tap rather than the tree, which has nothing for
+// items added without a field to attach them to.
+pub(crate) struct ExpertCollector {
+  tapped: Box<RefCell<Vec<TappedExpertInfo>>>,
+}
+
+impl Drop for ExpertCollector {
+  fn drop(&mut self) {
+    unsafe {
+      raw::remove_tap_listener(self.tap_data());
+    }
+  }
+}
+
+impl ExpertCollector {
+  // Errors with the message register_tap_listener() gave.
+  pub(crate) fn register() -> Result<ExpertCollector, String> {
+    let collector = ExpertCollector {
+      tapped: Box::new(RefCell::new(Vec::new())),
+    };
+
+    let tap_name = CString::new("expert").unwrap();
+
+    unsafe {
+      let err = raw::register_tap_listener(
+        tap_name.as_ptr(),
+        collector.tap_data(),
+        std::ptr::null(),
+        0,
+        None,
+        Some(expert_packet),
+        None,
+        None,
+      );
+
+      if !err.is_null() {
+        let msg = utils::cstr_to_string((*err).str_);
+        raw::g_string_free(err, true);
+
+        return Err(msg);
+      }
+    }
+
+    Ok(collector)
+  }
+
+  fn tap_data(&self) -> *mut c_void {
+    self.tapped.as_ref() as *const RefCell<Vec<TappedExpertInfo>> as *mut c_void
+  }
+
+  pub(crate) fn finish(self, edt: &Rc<RefCell<Pin<Box<Edt>>>>) -> Vec<ExpertInfo> {
+    self
+      .tapped
+      .take()
+      .into_iter()
+      .filter_map(|info| {
+        let node = (!info.pitem.is_null())
+          .then(|| ProtoNode::new(edt.clone(), info.pitem, unsafe { node_depth(info.pitem) }));
+
+        Some(ExpertInfo {
+          severity: ExpertSeverity::from_raw(info.severity)?,
+          group: ExpertGroup::from_raw(info.group),
+          protocol: info.protocol,
+          abbrev: info.abbrev,
+          message: info.message,
+          node,
+        })
+      })
+      .collect()
+  }
+}
+
+unsafe extern "C" fn expert_packet(
+  tapdata: *mut c_void,
+  _pinfo: *mut raw::packet_info,
+  _edt: *mut raw::epan_dissect_t,
+  data: *const c_void,
+  _flags: raw::tap_flags_t,
+) -> raw::tap_packet_status {
+  unsafe {
+    let tapped = &*(tapdata as *const RefCell<Vec<TappedExpertInfo>>);
+
+    if let Some(info) = TappedExpertInfo::from_tap(data) {
+      tapped.borrow_mut().push(info);
+    }
+  }
+
+  raw::tap_packet_status_TAP_PACKET_DONT_REDRAW
+}
+
+impl TappedExpertInfo {
+  unsafe fn from_tap(data: *const c_void) -> Option<TappedExpertInfo> {
+    unsafe {
+      let info = (data as *const raw::expert_info_t).as_ref()?;
+
+      let abbrev = if info.hf_index >= 0 {
+        utils::opt_cstr_to_string(raw::proto_registrar_get_abbrev(info.hf_index))
+      } else {
+        None
+      };
+
+      Some(TappedExpertInfo {
+        severity: info.severity as u32,
+        group: info.group as u32,
+        protocol: utils::opt_cstr_to_string(info.protocol),
+        abbrev: abbrev.unwrap_or_else(|| "_ws.expert.message".to_string()),
+        message: utils::opt_cstr_to_string(info.summary).unwrap_or_default(),
+        pitem: info.pitem,
+      })
+    }
+  }
+}
+
+// ProtoNode depths count from the tree's root at 0.
+unsafe fn node_depth(node: *mut raw::proto_node) -> i16 {
+  let mut depth = 0;
+
+  unsafe {
+    let mut parent = (*node).parent;
+
+    while !parent.is_null() {
+      depth += 1;
+      parent = (*parent).parent;
+    }
+  }
+
+  depth
+}
+
+impl ParsedRec {
+  pub fn expert_infos(&self) -> Result<Vec<ExpertInfo>, Error> {
+    match self.expert_infos {
+      None => Err(Error::NotDissected),
+      Some(Ok(ref infos)) => Ok(infos.clone()),
+      Some(Err(ref msg)) => Err(Error::Tap(msg.clone())),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExpertSummaryEntry {
+  pub severity: ExpertSeverity,
+  pub group: ExpertGroup,
+  pub protocol: Option<String>,
+  pub abbrev: String,
+  // The message of the first occurrence.
+  pub message: String,
+  pub frames: Vec<u32>,
+}
+
+// Groups expert items over a capture the way Wireshark's Expert Information
+// dialog does.
+#[derive(Debug, Default)]
+pub struct ExpertSummary {
+  entries: Vec<ExpertSummaryEntry>,
+  index: HashMap<(ExpertSeverity, ExpertGroup, Option<String>, String), usize>,
+}
+
+impl ExpertSummary {
+  pub fn new() -> ExpertSummary {
+    ExpertSummary::default()
+  }
+
+  pub fn add(&mut self, prec: &ParsedRec) -> Result<(), Error> {
+    let frame_num = prec.get_frame_number();
+
+    for info in prec.expert_infos()? {
+      let key = (
+        info.severity,
+        info.group,
+        info.protocol.clone(),
+        info.abbrev.clone(),
+      );
+
+      let idx = *self.index.entry(key).or_insert_with(|| {
+        self.entries.push(ExpertSummaryEntry {
+          severity: info.severity,
+          group: info.group,
+          protocol: info.protocol,
+          abbrev: info.abbrev,
+          message: info.message,
+          frames: Vec::new(),
+        });
+
+        self.entries.len() - 1
+      });
+
+      let frames = &mut self.entries[idx].frames;
+
+      if frames.last() != Some(&frame_num) {
+        frames.push(frame_num);
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn entries(&self) -> &[ExpertSummaryEntry] {
+    &self.entries
+  }
+
+  pub fn count(&self, severity: ExpertSeverity) -> usize {
+    self
+      .entries
+      .iter()
+      .filter(|e| e.severity == severity)
+      .map(|e| e.frames.len())
+      .sum()
+  }
+
+  pub fn get_max_severity(&self) -> Option<ExpertSeverity> {
+    self.entries.iter().map(|e| e.severity).max()
+  }
+
+  pub fn get_malformed_frames(&self) -> Vec<u32> {
+    let mut frames: Vec<u32> = self
+      .entries
+      .iter()
+      .filter(|e| e.group == ExpertGroup::Malformed)
+      .flat_map(|e| e.frames.iter().copied())
+      .collect();
+
+    frames.sort_unstable();
+    frames.dedup();
+    frames
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn expert_tap_listened() -> bool {
+    let tap_name = CString::new("expert").unwrap();
+
+    unsafe { raw::have_tap_listener(raw::find_tap_id(tap_name.as_ptr())) }
+  }
+
+  #[test]
+  fn collector_lives_for_one_dissection() {
+    let _guard = utils::init_for_tests();
+
+    let session = Session::new();
+    let mut wtap =
+      Wtap::new(concat!(env!("CARGO_MANIFEST_DIR"), "/data/1.pcap").to_string()).unwrap();
+    let mut rec = wtap.read().unwrap();
+    let mut prec = session.new_prec(&mut rec);
+
+    assert!(matches!(prec.expert_infos(), Err(Error::NotDissected)));
+
+    prec.dissect();
+
+    assert!(prec.expert_infos().is_ok());
+    assert!(!expert_tap_listened());
+
+    let collector = ExpertCollector::register().unwrap();
+
+    assert!(expert_tap_listened());
+
+    drop(collector);
+
+    assert!(!expert_tap_listened());
+  }
+}
@@ -0,0 +1,661 @@
+use std::fs::{self, OpenOptions};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::utils;
+use crate::*;
+
+#[derive(Debug, Clone)]
+pub struct ExtcapBinary {
+  pub path: PathBuf,
+  pub version: Option<String>,
+  pub help: Option<String>,
+  pub interfaces: Vec<ExtcapInterface>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtcapInterface {
+  pub binary: PathBuf,
+  pub value: String,
+  pub display: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtcapDlt {
+  pub number: i32,
+  pub name: String,
+  pub display: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtcapArgType {
+  Integer,
+  Unsigned,
+  Long,
+  Double,
+  String,
+  Password,
+  Boolean,
+  BoolFlag,
+  FileSelect,
+  Selector,
+  EditSelector,
+  Radio,
+  Multicheck,
+  Timestamp,
+  Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtcapValue {
+  pub value: String,
+  pub display: String,
+  pub default: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtcapArg {
+  pub number: u32,
+  pub call: String,
+  pub display: String,
+  pub arg_type: ExtcapArgType,
+  pub default: Option<String>,
+  pub range: Option<String>,
+  pub tooltip: Option<String>,
+  pub required: bool,
+  pub values: Vec<ExtcapValue>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExtcapArgValue {
+  Integer(i64),
+  Double(f64),
+  Boolean(bool),
+  String(String),
+}
+
+// A parsed "keyword {key=value}{key=value}..." line.
+struct Sentence {
+  keyword: String,
+  params: Vec<(String, String)>,
+}
+
+impl Sentence {
+  fn parse(line: &str) -> Option<Sentence> {
+    let line = line.trim();
+    let (keyword, mut rest) = line.split_once(' ')?;
+    let mut params = Vec::new();
+
+    while let Some(start) = rest.find('{') {
+      rest = &rest[start + 1..];
+
+      let Some(end) = param_end(rest) else {
+        break;
+      };
+
+      // A {key} without a value is skipped, not the whole sentence.
+      if let Some((key, value)) = rest[..end].split_once('=') {
+        params.push((key.trim().to_string(), value.to_string()));
+      }
+
+      rest = &rest[end + 1..];
+    }
+
+    Some(Sentence {
+      keyword: keyword.to_string(),
+      params,
+    })
+  }
+
+  fn get(&self, key: &str) -> Option<String> {
+    self
+      .params
+      .iter()
+      .find(|(k, _)| k == key)
+      .map(|(_, v)| v.clone())
+  }
+}
+
+// A value may contain '}' itself; only one followed by the next '{',
+// whitespace or the end of the line closes it.
+fn param_end(s: &str) -> Option<usize> {
+  s.match_indices('}').map(|(i, _)| i).find(|&i| {
+    s[i + 1..]
+      .chars()
+      .next()
+      .is_none_or(|c| c == '{' || c.is_whitespace())
+  })
+}
+
+fn run_extcap(binary: &Path, args: &[&str]) -> Result<Vec<Sentence>, Error> {
+  let output = Command::new(binary).args(args).output()?;
+
+  if !output.status.success() {
+    return Err(Error::Extcap(format!(
+      "{} {} failed: {}",
+      binary.display(),
+      args.join(" "),
+      String::from_utf8_lossy(&output.stderr).trim()
+    )));
+  }
+
+  Ok(
+    String::from_utf8_lossy(&output.stdout)
+      .lines()
+      .filter_map(Sentence::parse)
+      .collect(),
+  )
+}
+
+pub fn extcap_dirs() -> Vec<PathBuf> {
+  let mut dirs = Vec::new();
+
+  unsafe {
+    let pers_dir = raw::get_extcap_pers_dir();
+
+    if !pers_dir.is_null() {
+      dirs.push(PathBuf::from(utils::cstr_to_string(pers_dir)));
+      raw::g_free(pers_dir as raw::gpointer);
+    }
+
+    if let Some(dir) = utils::opt_cstr_to_string(raw::get_extcap_dir()) {
+      dirs.push(PathBuf::from(dir));
+    }
+  }
+
+  dirs
+}
+
+pub fn find_extcap_binaries() -> Vec<PathBuf> {
+  let mut binaries = Vec::new();
+
+  for dir in extcap_dirs() {
+    let Ok(entries) = fs::read_dir(dir) else {
+      continue;
+    };
+
+    for entry in entries.flatten() {
+      let Ok(metadata) = entry.metadata() else {
+        continue;
+      };
+
+      if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0 {
+        binaries.push(entry.path());
+      }
+    }
+  }
+
+  binaries.sort();
+  binaries
+}
+
+impl ExtcapBinary {
+  pub fn query(path: PathBuf) -> Result<ExtcapBinary, Error> {
+    let sentences = run_extcap(&path, &["--extcap-interfaces"])?;
+
+    let mut binary = ExtcapBinary {
+      path,
+      version: None,
+      help: None,
+      interfaces: Vec::new(),
+    };
+
+    for sentence in sentences {
+      match sentence.keyword.as_str() {
+        "extcap" => {
+          binary.version = sentence.get("version");
+          binary.help = sentence.get("help");
+        }
+        "interface" => {
+          if let Some(value) = sentence.get("value") {
+            binary.interfaces.push(ExtcapInterface {
+              binary: binary.path.clone(),
+              display: sentence.get("display").unwrap_or_else(|| value.clone()),
+              value,
+            });
+          }
+        }
+        _ => {}
+      }
+    }
+
+    Ok(binary)
+  }
+}
+
+impl ExtcapInterface {
+  pub fn dlts(&self) -> Result<Vec<ExtcapDlt>, Error> {
+    let sentences = run_extcap(
+      &self.binary,
+      &["--extcap-dlts", "--extcap-interface", &self.value],
+    )?;
+
+    Ok(
+      sentences
+        .into_iter()
+        .filter(|s| s.keyword == "dlt")
+        .filter_map(|s| {
+          Some(ExtcapDlt {
+            number: s.get("number")?.parse().ok()?,
+            name: s.get("name").unwrap_or_default(),
+            display: s.get("display").unwrap_or_default(),
+          })
+        })
+        .collect(),
+    )
+  }
+
+  pub fn config(&self) -> Result<Vec<ExtcapArg>, Error> {
+    let sentences = run_extcap(
+      &self.binary,
+      &["--extcap-config", "--extcap-interface", &self.value],
+    )?;
+
+    let mut args: Vec<ExtcapArg> = Vec::new();
+
+    for sentence in sentences {
+      match sentence.keyword.as_str() {
+        "arg" => {
+          let (Some(number), Some(call)) = (
+            sentence.get("number").and_then(|n| n.parse().ok()),
+            sentence.get("call"),
+          ) else {
+            continue;
+          };
+
+          args.push(ExtcapArg {
+            number,
+            display: sentence.get("display").unwrap_or_else(|| call.clone()),
+            call,
+            arg_type: ExtcapArgType::from_name(&sentence.get("type").unwrap_or_default()),
+            default: sentence.get("default"),
+            range: sentence.get("range"),
+            tooltip: sentence.get("tooltip"),
+            required: sentence.get("required").is_some_and(|r| r == "true"),
+            values: Vec::new(),
+          });
+        }
+        "value" => {
+          let number: Option<u32> = sentence.get("arg").and_then(|n| n.parse().ok());
+          let arg = args.iter_mut().find(|a| Some(a.number) == number);
+
+          if let (Some(arg), Some(value)) = (arg, sentence.get("value")) {
+            arg.values.push(ExtcapValue {
+              display: sentence.get("display").unwrap_or_else(|| value.clone()),
+              default: sentence.get("default").is_some_and(|d| d == "true"),
+              value,
+            });
+          }
+        }
+        _ => {}
+      }
+    }
+
+    Ok(args)
+  }
+}
+
+impl ExtcapArgType {
+  fn from_name(name: &str) -> ExtcapArgType {
+    match name {
+      "integer" => ExtcapArgType::Integer,
+      "unsigned" => ExtcapArgType::Unsigned,
+      "long" => ExtcapArgType::Long,
+      "double" => ExtcapArgType::Double,
+      "string" => ExtcapArgType::String,
+      "password" => ExtcapArgType::Password,
+      "boolean" => ExtcapArgType::Boolean,
+      "boolflag" => ExtcapArgType::BoolFlag,
+      "fileselect" => ExtcapArgType::FileSelect,
+      "selector" => ExtcapArgType::Selector,
+      "editselector" => ExtcapArgType::EditSelector,
+      "radio" => ExtcapArgType::Radio,
+      "multicheck" => ExtcapArgType::Multicheck,
+      "timestamp" => ExtcapArgType::Timestamp,
+      _ => ExtcapArgType::Unknown,
+    }
+  }
+}
+
+impl ExtcapArg {
+  pub fn to_cli_args(&self, value: &ExtcapArgValue) -> Result<Vec<String>, Error> {
+    let value = match (self.arg_type, value) {
+      (ExtcapArgType::BoolFlag, ExtcapArgValue::Boolean(set)) => {
+        return Ok(if *set {
+          vec![self.call.clone()]
+        } else {
+          vec![]
+        });
+      }
+      (ExtcapArgType::Boolean, ExtcapArgValue::Boolean(b)) => b.to_string(),
+      (
+        ExtcapArgType::Integer
+        | ExtcapArgType::Unsigned
+        | ExtcapArgType::Long
+        | ExtcapArgType::Timestamp,
+        ExtcapArgValue::Integer(i),
+      ) => i.to_string(),
+      (ExtcapArgType::Double, ExtcapArgValue::Double(d)) => d.to_string(),
+      (ExtcapArgType::Double, ExtcapArgValue::Integer(i)) => i.to_string(),
+      (
+        ExtcapArgType::String
+        | ExtcapArgType::Password
+        | ExtcapArgType::FileSelect
+        | ExtcapArgType::Selector
+        | ExtcapArgType::EditSelector
+        | ExtcapArgType::Radio
+        | ExtcapArgType::Multicheck
+        | ExtcapArgType::Unknown,
+        ExtcapArgValue::String(s),
+      ) => s.clone(),
+      (arg_type, value) => {
+        return Err(Error::Extcap(format!(
+          "{}: {:?} is not a valid value for a {:?} argument",
+          self.call, value, arg_type
+        )));
+      }
+    };
+
+    Ok(vec![self.call.clone(), value])
+  }
+}
+
+pub struct ExtcapCapture {
+  wtap: Wtap,
+  child: Arc<Mutex<Child>>,
+  fifo: PathBuf,
+}
+
+impl Drop for ExtcapCapture {
+  fn drop(&mut self) {
+    if let Ok(mut child) = self.child.lock() {
+      let _ = child.kill();
+      let _ = child.wait();
+    }
+
+    let _ = fs::remove_file(&self.fifo);
+  }
+}
+
+impl ExtcapCapture {
+  pub fn new(
+    interface: &ExtcapInterface,
+    args: &[(&ExtcapArg, ExtcapArgValue)],
+    capture_filter: Option<String>,
+  ) -> Result<ExtcapCapture, Error> {
+    let fifo = utils::make_fifo("extcap")?;
+
+    let mut command = Command::new(&interface.binary);
+
+    command
+      .arg("--capture")
+      .arg("--extcap-interface")
+      .arg(&interface.value)
+      .arg("--fifo")
+      .arg(&fifo)
+      .stdin(Stdio::null())
+      .stdout(Stdio::null());
+
+    if let Some(filter) = capture_filter {
+      command.arg("--extcap-capture-filter").arg(filter);
+    }
+
+    for (arg, value) in args {
+      match arg.to_cli_args(value) {
+        Ok(cli_args) => {
+          command.args(cli_args);
+        }
+        Err(e) => {
+          let _ = fs::remove_file(&fifo);
+          return Err(e);
+        }
+      }
+    }
+
+    let child = match command.spawn() {
+      Ok(child) => Arc::new(Mutex::new(child)),
+      Err(e) => {
+        let _ = fs::remove_file(&fifo);
+        return Err(Error::IoError(e));
+      }
+    };
+
+    // Opening the FIFO blocks until the extcap opens it for writing. If the
+    // extcap exits before that, open the write end ourselves so that the
+    // open fails on EOF instead of hanging.
+    let opened = Arc::new(AtomicBool::new(false));
+
+    {
+      let (child, opened, fifo) = (child.clone(), opened.clone(), fifo.clone());
+
+      std::thread::spawn(move || {
+        while !opened.load(Ordering::Acquire) {
+          let exited = child.lock().map(|mut c| !matches!(c.try_wait(), Ok(None)));
+
+          if exited.unwrap_or(true) {
+            let _ = OpenOptions::new().write(true).open(&fifo);
+            break;
+          }
+
+          std::thread::sleep(Duration::from_millis(100));
+        }
+      });
+    }
+
+    let wtap = Wtap::new(fifo.to_string_lossy().into_owned());
+
+    opened.store(true, Ordering::Release);
+
+    match wtap {
+      Ok(wtap) => Ok(ExtcapCapture { wtap, child, fifo }),
+      Err(e) => {
+        if let Ok(mut child) = child.lock() {
+          let _ = child.kill();
+          let _ = child.wait();
+        }
+
+        let _ = fs::remove_file(&fifo);
+
+        Err(Error::WsError(e))
+      }
+    }
+  }
+
+  pub fn read(&mut self) -> Result<WtapRec, Error> {
+    self.wtap.read()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn script() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("data/extcap_example.sh")
+  }
+
+  fn arg(call: &str, arg_type: ExtcapArgType) -> ExtcapArg {
+    ExtcapArg {
+      number: 0,
+      call: call.to_string(),
+      display: call.to_string(),
+      arg_type,
+      default: None,
+      range: None,
+      tooltip: None,
+      required: false,
+      values: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn parse_sentence() {
+    let s = Sentence::parse("arg {number=0}{call=--delay}{display=Delay}").unwrap();
+
+    assert_eq!(s.keyword, "arg");
+    assert_eq!(s.get("number").as_deref(), Some("0"));
+    assert_eq!(s.get("call").as_deref(), Some("--delay"));
+    assert_eq!(s.get("display").as_deref(), Some("Delay"));
+    assert_eq!(s.get("type"), None);
+  }
+
+  #[test]
+  fn parse_sentence_without_params() {
+    assert!(Sentence::parse("garbage").is_none());
+    assert!(Sentence::parse("extcap ").is_none());
+
+    let s = Sentence::parse("interface {value}").unwrap();
+
+    assert_eq!(s.keyword, "interface");
+    assert!(s.params.is_empty());
+  }
+
+  #[test]
+  fn parse_skips_param_without_value() {
+    let s = Sentence::parse("arg {number=1}{reload}{call=--remote}").unwrap();
+
+    assert_eq!(s.params.len(), 2);
+    assert_eq!(s.get("call").as_deref(), Some("--remote"));
+  }
+
+  #[test]
+  fn parse_brace_in_value() {
+    let s = Sentence::parse("extcap {help=https://example.com/{docs}}{version=1}").unwrap();
+
+    assert_eq!(s.get("help").as_deref(), Some("https://example.com/{docs}"));
+    assert_eq!(s.get("version").as_deref(), Some("1"));
+
+    let s = Sentence::parse("value {arg=0}{display=a}b} c}").unwrap();
+
+    assert_eq!(s.get("display").as_deref(), Some("a}b"));
+  }
+
+  #[test]
+  fn parse_unterminated_param() {
+    let s = Sentence::parse("arg {number=1}{call=--x").unwrap();
+
+    assert_eq!(s.params, vec![("number".to_string(), "1".to_string())]);
+  }
+
+  #[test]
+  fn arg_type_from_name() {
+    assert_eq!(ExtcapArgType::from_name("integer"), ExtcapArgType::Integer);
+    assert_eq!(
+      ExtcapArgType::from_name("boolflag"),
+      ExtcapArgType::BoolFlag
+    );
+    assert_eq!(
+      ExtcapArgType::from_name("multicheck"),
+      ExtcapArgType::Multicheck
+    );
+    assert_eq!(ExtcapArgType::from_name("Integer"), ExtcapArgType::Unknown);
+    assert_eq!(ExtcapArgType::from_name(""), ExtcapArgType::Unknown);
+  }
+
+  #[test]
+  fn cli_args() {
+    let delay = arg("--delay", ExtcapArgType::Integer);
+
+    assert_eq!(
+      delay.to_cli_args(&ExtcapArgValue::Integer(5)).unwrap(),
+      vec!["--delay", "5"]
+    );
+    assert!(delay.to_cli_args(&ExtcapArgValue::Boolean(true)).is_err());
+
+    let verify = arg("--verify", ExtcapArgType::BoolFlag);
+
+    assert_eq!(
+      verify.to_cli_args(&ExtcapArgValue::Boolean(true)).unwrap(),
+      vec!["--verify"]
+    );
+    assert!(
+      verify
+        .to_cli_args(&ExtcapArgValue::Boolean(false))
+        .unwrap()
+        .is_empty()
+    );
+
+    let ratio = arg("--ratio", ExtcapArgType::Double);
+
+    assert_eq!(
+      ratio.to_cli_args(&ExtcapArgValue::Integer(2)).unwrap(),
+      vec!["--ratio", "2"]
+    );
+
+    let remote = arg("--remote", ExtcapArgType::Selector);
+
+    assert_eq!(
+      remote
+        .to_cli_args(&ExtcapArgValue::String("if1".to_string()))
+        .unwrap(),
+      vec!["--remote", "if1"]
+    );
+  }
+
+  #[test]
+  fn query_script() {
+    let binary = ExtcapBinary::query(script()).unwrap();
+
+    assert_eq!(binary.version.as_deref(), Some("1.0"));
+    assert_eq!(binary.help.as_deref(), Some("https://example.com/{docs}"));
+    assert_eq!(binary.interfaces.len(), 1);
+
+    let interface = &binary.interfaces[0];
+
+    assert_eq!(interface.value, "example");
+    assert_eq!(interface.display, "Example interface");
+
+    let dlts = interface.dlts().unwrap();
+
+    assert_eq!(dlts.len(), 1);
+    assert_eq!(dlts[0].number, 1);
+    assert_eq!(dlts[0].name, "EN10MB");
+
+    let args = interface.config().unwrap();
+
+    assert_eq!(args.len(), 4);
+    assert_eq!(args[0].arg_type, ExtcapArgType::Integer);
+    assert_eq!(args[0].default.as_deref(), Some("5"));
+    assert_eq!(args[0].range.as_deref(), Some("1,15"));
+    assert!(args[1].required);
+    assert_eq!(args[2].arg_type, ExtcapArgType::BoolFlag);
+    assert_eq!(args[3].values.len(), 2);
+    assert!(args[3].values[0].default);
+    assert_eq!(args[3].values[1].display, "Remote 2");
+  }
+
+  #[test]
+  fn capture_from_script() {
+    let _guard = utils::init_for_tests();
+
+    let binary = ExtcapBinary::query(script()).unwrap();
+    let interface = &binary.interfaces[0];
+    let args = interface.config().unwrap();
+
+    let mut capture = ExtcapCapture::new(
+      interface,
+      &[
+        (&args[0], ExtcapArgValue::Integer(1)),
+        (&args[2], ExtcapArgValue::Boolean(true)),
+      ],
+      None,
+    )
+    .unwrap();
+
+    let mut count = 0;
+
+    loop {
+      match capture.read() {
+        Ok(_) => count += 1,
+        Err(Error::EOF) => break,
+        Err(e) => panic!("{:?}", e),
+      }
+    }
+
+    assert_eq!(count, 6);
+  }
+}
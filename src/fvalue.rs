@@ -2,6 +2,7 @@ use std::ffi::CStr;
 use std::{cell::RefCell, pin::Pin, rc::Rc};
 
 use crate::Edt;
+use crate::NsTime;
 use crate::raw;
 
 #[derive(Debug)]
@@ -51,6 +52,75 @@ impl FValue {
     unsafe { FType::new((*self.fvalue).ftype) }
   }
 
+  // None unless the field is an unsigned integer of at most 32 bits.
+  pub fn get_uinteger(&self) -> Option<u32> {
+    match self.get_ftype().get_id() {
+      raw::ftenum_FT_CHAR
+      | raw::ftenum_FT_UINT8
+      | raw::ftenum_FT_UINT16
+      | raw::ftenum_FT_UINT24
+      | raw::ftenum_FT_UINT32
+      | raw::ftenum_FT_FRAMENUM => unsafe { Some(raw::fvalue_get_uinteger(self.fvalue)) },
+      _ => None,
+    }
+  }
+
+  // Integers, floating point numbers and relative times (in seconds).
+  pub fn get_number(&self) -> Option<f64> {
+    unsafe {
+      match self.get_ftype().get_id() {
+        raw::ftenum_FT_CHAR
+        | raw::ftenum_FT_UINT8
+        | raw::ftenum_FT_UINT16
+        | raw::ftenum_FT_UINT24
+        | raw::ftenum_FT_UINT32
+        | raw::ftenum_FT_FRAMENUM => Some(raw::fvalue_get_uinteger(self.fvalue) as f64),
+        raw::ftenum_FT_UINT40
+        | raw::ftenum_FT_UINT48
+        | raw::ftenum_FT_UINT56
+        | raw::ftenum_FT_UINT64 => Some(raw::fvalue_get_uinteger64(self.fvalue) as f64),
+        raw::ftenum_FT_INT8
+        | raw::ftenum_FT_INT16
+        | raw::ftenum_FT_INT24
+        | raw::ftenum_FT_INT32 => Some(raw::fvalue_get_sinteger(self.fvalue) as f64),
+        raw::ftenum_FT_INT40
+        | raw::ftenum_FT_INT48
+        | raw::ftenum_FT_INT56
+        | raw::ftenum_FT_INT64 => Some(raw::fvalue_get_sinteger64(self.fvalue) as f64),
+        raw::ftenum_FT_FLOAT | raw::ftenum_FT_DOUBLE => Some(raw::fvalue_get_floating(self.fvalue)),
+        raw::ftenum_FT_RELATIVE_TIME => {
+          let ts = raw::fvalue_get_time(self.fvalue);
+
+          if ts.is_null() {
+            None
+          } else {
+            Some(NsTime::from(*ts).as_secs_f64())
+          }
+        }
+        _ => None,
+      }
+    }
+  }
+
+  pub fn get_string(&self) -> Option<String> {
+    match self.get_ftype().get_id() {
+      raw::ftenum_FT_STRING
+      | raw::ftenum_FT_STRINGZ
+      | raw::ftenum_FT_UINT_STRING
+      | raw::ftenum_FT_STRINGZPAD
+      | raw::ftenum_FT_STRINGZTRUNC => unsafe {
+        let char_ptr = raw::fvalue_get_string(self.fvalue);
+
+        if char_ptr.is_null() {
+          None
+        } else {
+          Some(CStr::from_ptr(char_ptr).to_string_lossy().into_owned())
+        }
+      },
+      _ => None,
+    }
+  }
+
   pub fn get_ftype_name(&self) -> String {
     unsafe {
       let char_ptr = raw::fvalue_type_name(self.fvalue);
@@ -0,0 +1,155 @@
+use std::ops::Range;
+
+const BYTES_PER_LINE: usize = 16;
+
+const HIGHLIGHT_ON: &str = "\x1b[7m";
+const HIGHLIGHT_OFF: &str = "\x1b[0m";
+
+// Formats `data` the way tshark -x does. Bytes in `highlight` are shown in
+// reverse video, as Wireshark does for the selected field.
+pub fn hex_dump(data: &[u8], highlight: Option<Range<usize>>) -> String {
+  let highlight = highlight.unwrap_or(0..0);
+  let offset_width = offset_width(data.len());
+
+  let mut out = String::new();
+
+  for (line, chunk) in data.chunks(BYTES_PER_LINE).enumerate() {
+    let line_offset = line * BYTES_PER_LINE;
+
+    let mut hex = String::new();
+    let mut ascii = String::new();
+
+    for (i, byte) in chunk.iter().enumerate() {
+      let highlighted = highlight.contains(&(line_offset + i));
+      let prev_highlighted = i > 0 && highlight.contains(&(line_offset + i - 1));
+
+      if i > 0 {
+        if highlighted && prev_highlighted {
+          hex.push(' ');
+        } else if prev_highlighted {
+          hex.push_str(HIGHLIGHT_OFF);
+          ascii.push_str(HIGHLIGHT_OFF);
+          hex.push(' ');
+        } else {
+          hex.push(' ');
+        }
+      }
+
+      if highlighted && !prev_highlighted {
+        hex.push_str(HIGHLIGHT_ON);
+        ascii.push_str(HIGHLIGHT_ON);
+      }
+
+      hex.push_str(&format!("{:02x}", byte));
+
+      ascii.push(if byte.is_ascii_graphic() || *byte == b' ' {
+        *byte as char
+      } else {
+        '.'
+      });
+    }
+
+    if highlight.contains(&(line_offset + chunk.len() - 1)) {
+      hex.push_str(HIGHLIGHT_OFF);
+      ascii.push_str(HIGHLIGHT_OFF);
+    }
+
+    let padding = (BYTES_PER_LINE - chunk.len()) * 3;
+
+    out.push_str(&format!(
+      "{:0width$x}  {}{}   {}\n",
+      line_offset,
+      hex,
+      " ".repeat(padding),
+      ascii,
+      width = offset_width
+    ));
+  }
+
+  out
+}
+
+// As many digits as the last offset needs, at least 4, like
+// print_hex_data_buffer().
+fn offset_width(len: usize) -> usize {
+  let last = len.saturating_sub(1);
+
+  (4..8).find(|digits| last >> (digits * 4) == 0).unwrap_or(8)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn highlight_across_lines() {
+    let dump = hex_dump(b"ABCDEFGHIJKLMNOPQRST", Some(14..18));
+    let lines: Vec<&str> = dump.lines().collect();
+
+    assert_eq!(
+      lines[0],
+      "0000  41 42 43 44 45 46 47 48 49 4a 4b 4c 4d 4e \x1b[7m4f 50\x1b[0m   \
+       ABCDEFGHIJKLMN\x1b[7mOP\x1b[0m"
+    );
+    assert_eq!(
+      lines[1],
+      format!(
+        "0010  \x1b[7m51 52\x1b[0m 53 54{}   \x1b[7mQR\x1b[0mST",
+        " ".repeat(36)
+      )
+    );
+  }
+
+  #[test]
+  fn highlight_to_end_of_data() {
+    let dump = hex_dump(b"ABC", Some(1..3));
+
+    assert_eq!(
+      dump,
+      format!(
+        "0000  41 \x1b[7m42 43\x1b[0m{}   A\x1b[7mBC\x1b[0m\n",
+        " ".repeat(39)
+      )
+    );
+  }
+
+  #[test]
+  fn partial_last_line() {
+    let dump = hex_dump(b"0123456789abcdefXYZ\x01", None);
+    let lines: Vec<&str> = dump.lines().collect();
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+      lines[0],
+      "0000  30 31 32 33 34 35 36 37 38 39 61 62 63 64 65 66   0123456789abcdef"
+    );
+    assert_eq!(
+      lines[1],
+      format!("0010  58 59 5a 01{}   XYZ.", " ".repeat(36))
+    );
+  }
+
+  #[test]
+  fn offset_width() {
+    let dump = hex_dump(&[0; 0x10000], None);
+
+    assert!(dump.lines().last().unwrap().starts_with("fff0  "));
+
+    let dump = hex_dump(&[0; 0x10001], None);
+    let lines: Vec<&str> = dump.lines().collect();
+
+    assert_eq!(lines.len(), 0x1001);
+    assert!(lines[0].starts_with("00000  00 00"));
+    assert!(lines[0x1000].starts_with("10000  00   "));
+
+    assert_eq!(super::offset_width(0), 4);
+    assert_eq!(super::offset_width(0x100000), 5);
+    assert_eq!(super::offset_width(0x100001), 6);
+    assert_eq!(super::offset_width(0x10000001), 8);
+  }
+
+  #[test]
+  fn empty() {
+    assert_eq!(hex_dump(&[], Some(0..4)), "");
+  }
+}
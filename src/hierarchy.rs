@@ -0,0 +1,197 @@
+use crate::*;
+
+#[derive(Debug, Clone)]
+pub struct ProtocolHierarchyNode {
+  pub protocol: String,
+  pub frames: u64,
+  pub bytes: u64,
+  // Frames in which this protocol was the last one.
+  pub end_frames: u64,
+  pub end_bytes: u64,
+  pub children: Vec<ProtocolHierarchyNode>,
+}
+
+impl ProtocolHierarchyNode {
+  fn new(protocol: String) -> ProtocolHierarchyNode {
+    ProtocolHierarchyNode {
+      protocol,
+      frames: 0,
+      bytes: 0,
+      end_frames: 0,
+      end_bytes: 0,
+      children: Vec::new(),
+    }
+  }
+}
+
+// Statistics -> Protocol Hierarchy: protocols are nested in the order they
+// appear at the top level of each packet's tree, e.g. frame/eth/ip/tcp/tls,
+// and bytes are whole frame lengths.
+#[derive(Debug, Clone, Default)]
+pub struct ProtocolHierarchy {
+  roots: Vec<ProtocolHierarchyNode>,
+  frames: u64,
+  bytes: u64,
+}
+
+impl ProtocolHierarchy {
+  pub fn new() -> ProtocolHierarchy {
+    ProtocolHierarchy::default()
+  }
+
+  // Dissects a whole file as the session's capture context, so the session
+  // must not have one yet.
+  pub fn from_file(
+    session: &Session,
+    path: String,
+    dfilter: Option<&DFilter>,
+  ) -> Result<ProtocolHierarchy, Error> {
+    let mut hierarchy = ProtocolHierarchy::new();
+    let dfilters: Vec<&DFilter> = dfilter.into_iter().collect();
+
+    context::dissect_file(session, path, &dfilters, |prec| {
+      if dfilter.is_none_or(|dfilter| dfilter.apply_rec(prec)) {
+        hierarchy.add(prec)?;
+      }
+
+      Ok(())
+    })?;
+
+    Ok(hierarchy)
+  }
+
+  pub fn add(&mut self, prec: &ParsedRec) -> Result<(), Error> {
+    let root = prec.get_root_node()?;
+
+    let len = prec.pkt_len as u64;
+
+    let path: Vec<String> = root
+      .iter_children()
+      .filter(|node| node.is_protocol())
+      .map(|node| node.get_abbrev())
+      .collect();
+
+    self.frames += 1;
+    self.bytes += len;
+
+    let mut level = &mut self.roots;
+
+    for (i, protocol) in path.iter().enumerate() {
+      let idx = match level.iter().position(|n| &n.protocol == protocol) {
+        Some(idx) => idx,
+        None => {
+          level.push(ProtocolHierarchyNode::new(protocol.clone()));
+          level.len() - 1
+        }
+      };
+
+      let node = &mut level[idx];
+
+      node.frames += 1;
+      node.bytes += len;
+
+      if i == path.len() - 1 {
+        node.end_frames += 1;
+        node.end_bytes += len;
+      }
+
+      level = &mut node.children;
+    }
+
+    Ok(())
+  }
+
+  pub fn get_roots(&self) -> &[ProtocolHierarchyNode] {
+    &self.roots
+  }
+
+  pub fn get_frames(&self) -> u64 {
+    self.frames
+  }
+
+  pub fn get_bytes(&self) -> u64 {
+    self.bytes
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn data(name: &str) -> String {
+    format!("{}/data/{}", env!("CARGO_MANIFEST_DIR"), name)
+  }
+
+  fn path(node: &ProtocolHierarchyNode) -> Vec<(String, u64, u64, u64)> {
+    let mut path = vec![(
+      node.protocol.clone(),
+      node.frames,
+      node.bytes,
+      node.end_frames,
+    )];
+
+    if let Some(child) = node.children.first() {
+      path.extend(self::path(child));
+    }
+
+    path
+  }
+
+  #[test]
+  fn dns_hierarchy() {
+    let _guard = utils::init_for_tests();
+
+    let hierarchy = ProtocolHierarchy::from_file(&Session::new(), data("1.pcap"), None).unwrap();
+
+    assert_eq!(hierarchy.get_frames(), 6);
+    assert_eq!(hierarchy.get_bytes(), 757);
+    assert_eq!(hierarchy.get_roots().len(), 1);
+
+    let expected: Vec<(String, u64, u64, u64)> = ["frame", "eth", "ip", "udp", "dns"]
+      .iter()
+      .enumerate()
+      .map(|(i, p)| (p.to_string(), 6, 757, if i == 4 { 6 } else { 0 }))
+      .collect();
+
+    assert_eq!(path(&hierarchy.get_roots()[0]), expected);
+  }
+
+  #[test]
+  fn filtered_hierarchy() {
+    let _guard = utils::init_for_tests();
+
+    let dfilter = DFilter::new("dns.flags.response == 1".to_string()).unwrap();
+    let hierarchy =
+      ProtocolHierarchy::from_file(&Session::new(), data("1.pcap"), Some(&dfilter)).unwrap();
+
+    assert_eq!(hierarchy.get_frames(), 3);
+    assert_eq!(hierarchy.get_bytes(), 487);
+  }
+
+  // Three frames of data/http.pcap end at TCP, the request and response
+  // carry HTTP.
+  #[test]
+  fn tcp_hierarchy() {
+    let _guard = utils::init_for_tests();
+
+    let hierarchy = ProtocolHierarchy::from_file(&Session::new(), data("http.pcap"), None).unwrap();
+
+    let tcp = &hierarchy.get_roots()[0].children[0].children[0].children[0];
+
+    assert_eq!(tcp.protocol, "tcp");
+    assert_eq!((tcp.frames, tcp.end_frames), (5, 3));
+    assert_eq!(tcp.children.len(), 1);
+    assert_eq!(tcp.children[0].protocol, "http");
+    assert_eq!(tcp.children[0].frames, 2);
+  }
+
+  #[test]
+  fn missing_file_keeps_context() {
+    let _guard = utils::init_for_tests();
+
+    let session = Session::new();
+
+    assert!(ProtocolHierarchy::from_file(&session, data("missing.pcap"), None).is_err());
+    assert!(session.new_capture_context().is_ok());
+  }
+}
@@ -0,0 +1,186 @@
+use std::ffi::CString;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::utils;
+use crate::*;
+
+#[derive(Debug, Clone)]
+pub struct InterfaceAddress {
+  pub addr: Option<IpAddr>,
+  pub netmask: Option<IpAddr>,
+  pub broadcast: Option<IpAddr>,
+  pub destination: Option<IpAddr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LinkType {
+  pub dlt: i32,
+  pub name: String,
+  pub description: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimestampType {
+  pub id: i32,
+  pub name: String,
+  pub description: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Interface {
+  pub name: String,
+  pub description: Option<String>,
+  pub addresses: Vec<InterfaceAddress>,
+  pub is_up: bool,
+  pub is_running: bool,
+  pub is_loopback: bool,
+  pub is_wireless: bool,
+  pub link_types: Vec<LinkType>,
+  pub timestamp_types: Vec<TimestampType>,
+}
+
+pub fn list_interfaces() -> Result<Vec<Interface>, Error> {
+  let mut errbuf = [0 as raw::gchar; raw::PCAP_ERRBUF_SIZE as usize];
+  let mut alldevs: *mut raw::pcap_if_t = std::ptr::null_mut();
+
+  unsafe {
+    if raw::pcap_findalldevs(
+      (&mut alldevs) as *mut *mut raw::pcap_if_t,
+      errbuf.as_mut_ptr(),
+    ) < 0
+    {
+      return Err(Error::CaptureError(utils::cstr_to_string(errbuf.as_ptr())));
+    }
+
+    let mut interfaces = Vec::new();
+    let mut dev = alldevs;
+
+    while !dev.is_null() {
+      let name = utils::cstr_to_string((*dev).name);
+      let (link_types, timestamp_types) = query_capabilities(&name);
+
+      interfaces.push(Interface {
+        description: utils::opt_cstr_to_string((*dev).description),
+        addresses: get_addresses((*dev).addresses),
+        is_up: (*dev).flags & raw::PCAP_IF_UP != 0,
+        is_running: (*dev).flags & raw::PCAP_IF_RUNNING != 0,
+        is_loopback: (*dev).flags & raw::PCAP_IF_LOOPBACK != 0,
+        is_wireless: (*dev).flags & raw::PCAP_IF_WIRELESS != 0,
+        name,
+        link_types,
+        timestamp_types,
+      });
+
+      dev = (*dev).next;
+    }
+
+    raw::pcap_freealldevs(alldevs);
+
+    Ok(interfaces)
+  }
+}
+
+fn get_addresses(mut addr: *mut raw::pcap_addr_t) -> Vec<InterfaceAddress> {
+  let mut addresses = Vec::new();
+
+  while !addr.is_null() {
+    unsafe {
+      addresses.push(InterfaceAddress {
+        addr: sockaddr_to_ip((*addr).addr),
+        netmask: sockaddr_to_ip((*addr).netmask),
+        broadcast: sockaddr_to_ip((*addr).broadaddr),
+        destination: sockaddr_to_ip((*addr).dstaddr),
+      });
+
+      addr = (*addr).next;
+    }
+  }
+
+  addresses
+}
+
+fn sockaddr_to_ip(addr: *const raw::sockaddr) -> Option<IpAddr> {
+  if addr.is_null() {
+    return None;
+  }
+
+  unsafe {
+    match (*addr).sa_family as u32 {
+      raw::AF_INET => {
+        let sin = addr as *const raw::sockaddr_in;
+
+        Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(
+          (*sin).sin_addr.s_addr,
+        ))))
+      }
+      raw::AF_INET6 => {
+        let sin6 = addr as *const raw::sockaddr_in6;
+
+        Some(IpAddr::V6(Ipv6Addr::from(
+          (*sin6).sin6_addr.__in6_u.__u6_addr8,
+        )))
+      }
+      _ => None,
+    }
+  }
+}
+
+// Timestamp types can be listed on a handle that hasn't been activated, but
+// link-layer types need an activated one, which may fail without capture
+// privileges. In that case the link-layer list is left empty.
+fn query_capabilities(name: &str) -> (Vec<LinkType>, Vec<TimestampType>) {
+  let cname = CString::new(name).unwrap();
+  let mut errbuf = [0 as raw::gchar; raw::PCAP_ERRBUF_SIZE as usize];
+
+  let mut link_types = Vec::new();
+  let mut timestamp_types = Vec::new();
+
+  unsafe {
+    let pcap = raw::pcap_create(cname.as_ptr(), errbuf.as_mut_ptr());
+
+    if pcap.is_null() {
+      return (link_types, timestamp_types);
+    }
+
+    let mut tstamp_types: *mut ::std::os::raw::c_int = std::ptr::null_mut();
+
+    let count =
+      raw::pcap_list_tstamp_types(pcap, (&mut tstamp_types) as *mut *mut ::std::os::raw::c_int);
+
+    if count > 0 {
+      for id in std::slice::from_raw_parts(tstamp_types, count as usize) {
+        timestamp_types.push(TimestampType {
+          id: *id,
+          name: utils::opt_cstr_to_string(raw::pcap_tstamp_type_val_to_name(*id))
+            .unwrap_or_else(|| id.to_string()),
+          description: utils::opt_cstr_to_string(raw::pcap_tstamp_type_val_to_description(*id)),
+        });
+      }
+
+      raw::pcap_free_tstamp_types(tstamp_types);
+    }
+
+    if raw::pcap_activate(pcap) >= 0 {
+      let mut dlts: *mut ::std::os::raw::c_int = std::ptr::null_mut();
+
+      let count = raw::pcap_list_datalinks(pcap, (&mut dlts) as *mut *mut ::std::os::raw::c_int);
+
+      if count > 0 {
+        for dlt in std::slice::from_raw_parts(dlts, count as usize) {
+          link_types.push(LinkType {
+            dlt: *dlt,
+            name: utils::opt_cstr_to_string(raw::pcap_datalink_val_to_name(*dlt))
+              .unwrap_or_else(|| format!("DLT {}", dlt)),
+            description: utils::opt_cstr_to_string(raw::pcap_datalink_val_to_description(*dlt)),
+          });
+        }
+
+        raw::pcap_free_datalinks(dlts);
+      }
+    }
+
+    raw::pcap_close(pcap);
+  }
+
+  (link_types, timestamp_types)
+}
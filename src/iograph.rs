@@ -0,0 +1,358 @@
+use std::ffi::CString;
+use std::time::Duration;
+
+use crate::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IoMetric {
+  Packets,
+  Bytes,
+  // Over every occurrence of a numeric or relative time field.
+  Sum(String),
+  Min(String),
+  Max(String),
+  Avg(String),
+  // Time spent in each interval by a relative time field, such as smb.time,
+  // divided by the interval: the average number of outstanding requests.
+  // Each value counts back from its packet, over the intervals the request
+  // was outstanding in.
+  Load(String),
+}
+
+impl IoMetric {
+  fn get_field(&self) -> Option<&str> {
+    match self {
+      IoMetric::Packets | IoMetric::Bytes => None,
+      IoMetric::Sum(field)
+      | IoMetric::Min(field)
+      | IoMetric::Max(field)
+      | IoMetric::Avg(field)
+      | IoMetric::Load(field) => Some(field),
+    }
+  }
+}
+
+struct IoSeries {
+  name: String,
+  dfilter: Option<DFilter>,
+  metric: IoMetric,
+}
+
+#[derive(Debug, Clone)]
+pub struct IoSeriesData {
+  pub name: String,
+  pub metric: IoMetric,
+  // One value per interval, 0 where nothing matched.
+  pub values: Vec<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IoGraphData {
+  pub interval: Duration,
+  pub series: Vec<IoSeriesData>,
+}
+
+impl IoGraphData {
+  // Seconds since the first packet.
+  pub fn get_bucket_starts(&self) -> Vec<f64> {
+    let len = self.series.first().map_or(0, |s| s.values.len());
+
+    (0..len)
+      .map(|i| i as f64 * self.interval.as_secs_f64())
+      .collect()
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+  packets: u64,
+  bytes: u64,
+  samples: u64,
+  sum: f64,
+  min: Option<f64>,
+  max: Option<f64>,
+  load_us: u64,
+}
+
+impl Bucket {
+  fn value(&self, metric: &IoMetric, interval_us: u64) -> f64 {
+    match metric {
+      IoMetric::Packets => self.packets as f64,
+      IoMetric::Bytes => self.bytes as f64,
+      IoMetric::Sum(_) => self.sum,
+      IoMetric::Min(_) => self.min.unwrap_or(0.0),
+      IoMetric::Max(_) => self.max.unwrap_or(0.0),
+      IoMetric::Avg(_) if self.samples > 0 => self.sum / self.samples as f64,
+      IoMetric::Avg(_) => 0.0,
+      IoMetric::Load(_) => self.load_us as f64 / interval_us as f64,
+    }
+  }
+}
+
+// Statistics -> I/O Graphs, or tshark -z io,stat.
+pub struct IoGraph {
+  interval: Duration,
+  series: Vec<IoSeries>,
+}
+
+impl IoGraph {
+  pub fn new(interval: Duration) -> IoGraph {
+    IoGraph {
+      interval,
+      series: Vec::new(),
+    }
+  }
+
+  // Packets not matching `dfilter` don't count towards the series.
+  pub fn add_series(mut self, name: String, dfilter: Option<DFilter>, metric: IoMetric) -> IoGraph {
+    self.series.push(IoSeries {
+      name,
+      dfilter,
+      metric,
+    });
+
+    self
+  }
+
+  // Dissects the file as the session's capture context, as
+  // ProtocolHierarchy::from_file does. Intervals are counted in
+  // microseconds, as in Wireshark.
+  pub fn run(&self, session: &Session, path: String) -> Result<IoGraphData, Error> {
+    let interval_us = self.interval.as_micros() as u64;
+
+    if interval_us == 0 {
+      return Err(Error::InvalidInterval);
+    }
+
+    for series in self.series.iter() {
+      if let Some(field) = series.metric.get_field() {
+        check_field(field, &series.metric)?;
+      }
+    }
+
+    let dfilters: Vec<&DFilter> = self
+      .series
+      .iter()
+      .filter_map(|s| s.dfilter.as_ref())
+      .collect();
+
+    let mut buckets: Vec<Vec<Bucket>> = vec![Vec::new(); self.series.len()];
+    let mut first_us: Option<i64> = None;
+    let mut buckets_len = 0;
+
+    context::dissect_file(session, path, &dfilters, |prec| {
+      let ts = NsTime::from(prec.rec.borrow().rec.ts);
+      let ts_us = ts.secs * 1_000_000 + ts.nsecs as i64 / 1000;
+
+      let rel_us = (ts_us - *first_us.get_or_insert(ts_us)).max(0) as u64;
+      let idx = (rel_us / interval_us) as usize;
+
+      buckets_len = buckets_len.max(idx + 1);
+
+      let root = prec.get_root_node()?;
+
+      for (series, series_buckets) in self.series.iter().zip(buckets.iter_mut()) {
+        if let Some(ref dfilter) = series.dfilter {
+          if !dfilter.apply_rec(prec) {
+            continue;
+          }
+        }
+
+        if series_buckets.len() <= idx {
+          series_buckets.resize(idx + 1, Bucket::default());
+        }
+
+        let bucket = &mut series_buckets[idx];
+
+        bucket.packets += 1;
+        bucket.bytes += prec.pkt_len as u64;
+
+        let Some(field) = series.metric.get_field() else {
+          continue;
+        };
+
+        let values: Vec<f64> = root
+          .iter_depth_first()
+          .filter(|node| node.get_abbrev() == field)
+          .filter_map(|node| node.get_fvalue().and_then(|fv| fv.get_number()))
+          .collect();
+
+        for value in values {
+          if let IoMetric::Load(_) = series.metric {
+            add_load(
+              series_buckets,
+              idx,
+              rel_us % interval_us,
+              interval_us,
+              value,
+            );
+            continue;
+          }
+
+          let bucket = &mut series_buckets[idx];
+
+          bucket.samples += 1;
+          bucket.sum += value;
+          bucket.min = Some(bucket.min.map_or(value, |min| min.min(value)));
+          bucket.max = Some(bucket.max.map_or(value, |max| max.max(value)));
+        }
+      }
+
+      Ok(())
+    })?;
+
+    // Every series covers the whole capture.
+    let series = self
+      .series
+      .iter()
+      .zip(buckets.iter_mut())
+      .map(|(series, series_buckets)| {
+        series_buckets.resize(buckets_len, Bucket::default());
+
+        IoSeriesData {
+          name: series.name.clone(),
+          metric: series.metric.clone(),
+          values: series_buckets
+            .iter()
+            .map(|b| b.value(&series.metric, interval_us))
+            .collect(),
+        }
+      })
+      .collect();
+
+    Ok(IoGraphData {
+      interval: self.interval,
+      series,
+    })
+  }
+}
+
+// Spreads a request's `secs` over the interval of its response, from the
+// start of that interval to the response (`offset_us`), and the intervals
+// before it, as update_io_graph_item() does for LOAD.
+fn add_load(buckets: &mut [Bucket], idx: usize, offset_us: u64, interval_us: u64, secs: f64) {
+  if secs < 0.0 {
+    return;
+  }
+
+  let mut left = (secs * 1e6).round() as u64;
+  let mut span = offset_us.min(left);
+
+  for bucket in buckets[..=idx].iter_mut().rev() {
+    if left == 0 {
+      break;
+    }
+
+    bucket.load_us += span;
+    bucket.samples += 1;
+
+    left -= span;
+    span = interval_us.min(left);
+  }
+}
+
+// LOAD only makes sense for how long something took.
+fn check_field(field: &str, metric: &IoMetric) -> Result<(), Error> {
+  let cfield = CString::new(field).map_err(|_| Error::InvalidField(field.to_string()))?;
+
+  let hfinfo = unsafe { raw::proto_registrar_get_byname(cfield.as_ptr()) };
+
+  if hfinfo.is_null() {
+    return Err(Error::InvalidField(field.to_string()));
+  }
+
+  if let IoMetric::Load(_) = metric {
+    if unsafe { (*hfinfo).type_ } != raw::ftenum_FT_RELATIVE_TIME {
+      return Err(Error::InvalidField(field.to_string()));
+    }
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn run(interval: Duration, metric: IoMetric, dfilter: Option<&str>) -> Result<Vec<f64>, Error> {
+    let graph = IoGraph::new(interval).add_series(
+      "series".to_string(),
+      dfilter.map(|f| DFilter::new(f.to_string()).unwrap()),
+      metric,
+    );
+
+    let data = graph.run(
+      &Session::new(),
+      concat!(env!("CARGO_MANIFEST_DIR"), "/data/1.pcap").to_string(),
+    )?;
+
+    Ok(data.series[0].values.clone())
+  }
+
+  // data/1.pcap has two frames in its first second and four in the next.
+  #[test]
+  fn packets_and_bytes() {
+    let _guard = utils::init_for_tests();
+
+    let second = Duration::from_secs(1);
+
+    assert_eq!(
+      run(second, IoMetric::Packets, None).unwrap(),
+      vec![2.0, 4.0]
+    );
+    assert_eq!(
+      run(second, IoMetric::Bytes, None).unwrap(),
+      vec![243.0, 514.0]
+    );
+    assert_eq!(
+      run(second, IoMetric::Packets, Some("dns.flags.response == 1")).unwrap(),
+      vec![1.0, 2.0]
+    );
+  }
+
+  // The responses in frames 2, 5 and 6 took 17668, 969 and 18098
+  // microseconds, and arrived 17668, 1622136 and 1639326 microseconds into
+  // the capture.
+  #[test]
+  fn load_spreads_over_intervals() {
+    let _guard = utils::init_for_tests();
+
+    let values = run(
+      Duration::from_millis(10),
+      IoMetric::Load("dns.time".to_string()),
+      None,
+    )
+    .unwrap();
+
+    assert_eq!(values.len(), 164);
+
+    let mut expected = vec![0.0; 164];
+
+    expected[0] = 1.0;
+    expected[1] = 0.7668;
+    expected[162] = (969.0 + 8772.0) / 10000.0;
+    expected[163] = 0.9326;
+
+    for (value, expected) in values.iter().zip(expected) {
+      assert!((value - expected).abs() < 1e-9, "{} != {}", value, expected);
+    }
+  }
+
+  #[test]
+  fn load_needs_relative_time() {
+    let _guard = utils::init_for_tests();
+
+    assert!(matches!(
+      run(
+        Duration::from_secs(1),
+        IoMetric::Load("dns.id".to_string()),
+        None
+      ),
+      Err(Error::InvalidField(_))
+    ));
+    assert!(matches!(
+      run(Duration::from_nanos(10), IoMetric::Packets, None),
+      Err(Error::InvalidInterval)
+    ));
+  }
+}
@@ -6,18 +6,66 @@ pub mod raw {
   include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 }
 
+pub mod capture;
+pub mod cfilter;
+pub mod color;
+pub mod column;
+pub mod context;
+pub mod conversation;
+pub mod datasource;
+pub mod decode_as;
+pub mod decrypt;
 pub mod epan;
 pub mod error;
+pub mod expert;
+pub mod extcap;
 pub mod fvalue;
+pub mod hierarchy;
+pub mod hexdump;
+pub mod interface;
+pub mod iograph;
+pub mod prefs;
+pub mod print;
+pub mod profile;
 pub mod proto;
+pub mod protocols;
+pub mod provider;
+pub mod ringbuffer;
+pub mod stream;
+pub mod tap;
+pub mod twopass;
 pub mod wtap;
 pub mod dfilter;
 mod utils;
 
+pub use capture::*;
+pub use cfilter::*;
+pub use color::*;
+pub use column::*;
+pub use context::*;
+pub use conversation::*;
+pub use datasource::*;
+pub use decode_as::*;
+pub use decrypt::*;
 pub use epan::*;
 pub use error::*;
+pub use expert::*;
+pub use extcap::*;
 pub use fvalue::*;
+pub use hierarchy::*;
+pub use hexdump::*;
+pub use interface::*;
+pub use iograph::*;
+pub use prefs::*;
+pub use print::*;
+pub use profile::*;
 pub use proto::*;
+pub use protocols::*;
+pub use provider::*;
+pub use ringbuffer::*;
+pub use stream::*;
+pub use tap::*;
+pub use twopass::*;
 pub use wtap::*;
 pub use dfilter::*;
 
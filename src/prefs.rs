@@ -0,0 +1,182 @@
+use std::ffi::CString;
+use std::os::raw::{c_uint, c_void};
+
+use crate::Error;
+use crate::raw;
+use crate::utils;
+
+#[derive(Debug, Clone)]
+pub struct PrefInfo {
+  pub name: String,
+  pub title: Option<String>,
+  pub description: Option<String>,
+  pub pref_type: String,
+  pub default: Option<String>,
+  pub current: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrefModule {
+  pub name: String,
+  pub title: Option<String>,
+  pub description: Option<String>,
+  pub prefs: Vec<PrefInfo>,
+}
+
+pub struct Prefs;
+
+impl Prefs {
+  // Accepts anything `tshark -o` does, including "uat:<table>:<record>"
+  // when `name` is "uat".
+  pub fn set(name: &str, value: &str) -> Result<(), Error> {
+    let prefarg = CString::new(format!("{}:{}", name, value))
+      .map_err(|_| Error::InvalidPreference(name.to_string()))?
+      .into_raw();
+
+    let mut errmsg: *mut raw::gchar = std::ptr::null_mut();
+
+    unsafe {
+      let ret = raw::prefs_set_pref(prefarg, (&mut errmsg) as *mut *mut raw::gchar);
+
+      drop(CString::from_raw(prefarg));
+
+      let detail = if errmsg.is_null() {
+        None
+      } else {
+        let msg = utils::cstr_to_string(errmsg);
+        raw::g_free(errmsg as raw::gpointer);
+        Some(msg)
+      };
+
+      let reason = match ret {
+        raw::prefs_set_pref_e_PREFS_SET_OK => return Ok(()),
+        raw::prefs_set_pref_e_PREFS_SET_SYNTAX_ERR => "invalid value",
+        raw::prefs_set_pref_e_PREFS_SET_NO_SUCH_PREF => "no such preference",
+        raw::prefs_set_pref_e_PREFS_SET_OBSOLETE => "obsolete preference",
+        _ => "cannot set preference",
+      };
+
+      Err(Error::InvalidPreference(match detail {
+        Some(detail) => format!("{}: {} ({})", name, reason, detail),
+        None => format!("{}: {}", name, reason),
+      }))
+    }
+  }
+
+  pub fn get(name: &str) -> Result<String, Error> {
+    Prefs::pref_value(name, raw::pref_source_t_pref_current)
+  }
+
+  pub fn get_default(name: &str) -> Result<String, Error> {
+    Prefs::pref_value(name, raw::pref_source_t_pref_default)
+  }
+
+  // Runs the apply callbacks of every module whose preferences changed.
+  pub fn apply() {
+    unsafe {
+      raw::prefs_apply_all();
+    }
+  }
+
+  pub fn modules() -> Vec<PrefModule> {
+    let mut modules: Vec<PrefModule> = Vec::new();
+
+    unsafe {
+      raw::prefs_modules_foreach(
+        Some(collect_module),
+        (&mut modules) as *mut Vec<PrefModule> as *mut c_void,
+      );
+    }
+
+    modules.retain(|m| !m.prefs.is_empty());
+    modules
+  }
+
+  fn find(name: &str) -> Result<*mut raw::pref_t, Error> {
+    let not_found = || Error::InvalidPreference(format!("{}: no such preference", name));
+
+    let (module_name, pref_name) = name.split_once('.').ok_or_else(not_found)?;
+
+    let cmodule = CString::new(module_name).map_err(|_| not_found())?;
+    let cpref = CString::new(pref_name).map_err(|_| not_found())?;
+
+    unsafe {
+      let module = raw::prefs_find_module(cmodule.as_ptr());
+
+      if module.is_null() {
+        return Err(not_found());
+      }
+
+      let pref = raw::prefs_find_preference(module, cpref.as_ptr());
+
+      if pref.is_null() {
+        Err(not_found())
+      } else {
+        Ok(pref)
+      }
+    }
+  }
+
+  fn pref_value(name: &str, source: raw::pref_source_t) -> Result<String, Error> {
+    let pref = Prefs::find(name)?;
+
+    pref_to_string(pref, source).ok_or_else(|| Error::InvalidPreference(name.to_string()))
+  }
+}
+
+fn pref_to_string(pref: *mut raw::pref_t, source: raw::pref_source_t) -> Option<String> {
+  unsafe {
+    let value = raw::prefs_pref_to_str(pref, source);
+
+    if value.is_null() {
+      return None;
+    }
+
+    let s = utils::cstr_to_string(value);
+    raw::g_free(value as raw::gpointer);
+
+    Some(s)
+  }
+}
+
+unsafe extern "C" fn collect_module(module: *mut raw::module_t, user_data: *mut c_void) -> c_uint {
+  unsafe {
+    let modules = &mut *(user_data as *mut Vec<PrefModule>);
+
+    let mut pref_module = PrefModule {
+      name: utils::cstr_to_string((*module).name),
+      title: utils::opt_cstr_to_string((*module).title),
+      description: utils::opt_cstr_to_string((*module).description),
+      prefs: Vec::new(),
+    };
+
+    raw::prefs_pref_foreach(
+      module,
+      Some(collect_pref),
+      (&mut pref_module) as *mut PrefModule as *mut c_void,
+    );
+
+    modules.push(pref_module);
+  }
+
+  0
+}
+
+unsafe extern "C" fn collect_pref(pref: *mut raw::pref_t, user_data: *mut c_void) -> c_uint {
+  unsafe {
+    let module = &mut *(user_data as *mut PrefModule);
+
+    let name = utils::cstr_to_string(raw::prefs_get_name(pref));
+
+    module.prefs.push(PrefInfo {
+      name: format!("{}.{}", module.name, name),
+      title: utils::opt_cstr_to_string(raw::prefs_get_title(pref)),
+      description: utils::opt_cstr_to_string(raw::prefs_get_description(pref)),
+      pref_type: utils::opt_cstr_to_string(raw::prefs_pref_type_name(pref)).unwrap_or_default(),
+      default: pref_to_string(pref, raw::pref_source_t_pref_default),
+      current: pref_to_string(pref, raw::pref_source_t_pref_current),
+    });
+  }
+
+  0
+}
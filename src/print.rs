@@ -0,0 +1,360 @@
+use std::ffi::CString;
+use std::io::Write;
+
+use crate::utils;
+use crate::*;
+
+// Writes the same output as tshark's -T pdml/psml/json/ek and -V.
+impl ParsedRec {
+  // Only `fields` are written when given, as a flat list under the packet.
+  pub fn write_pdml<W: Write>(&self, w: &mut W, fields: &[&str]) -> Result<(), Error> {
+    self.check_dissected()?;
+
+    let output_fields = OutputFields::new(fields)?;
+    let cinfo = self.get_cinfo();
+
+    utils::with_memstream(w, |fh| unsafe {
+      raw::write_pdml_proto_tree(output_fields.fields, self.get_edt(), cinfo, fh, false);
+    })
+  }
+
+  // Needs columns set on the session.
+  pub fn write_psml<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+    self.check_dissected()?;
+
+    if self.get_cinfo().is_null() {
+      return Err(Error::NoColumns);
+    }
+
+    utils::with_memstream(w, |fh| unsafe {
+      raw::write_psml_columns(self.get_edt(), fh, false);
+    })
+  }
+
+  // Writes one packet object; tshark wraps these in an array.
+  pub fn write_json<W: Write>(&self, w: &mut W, fields: &[&str]) -> Result<(), Error> {
+    self.check_dissected()?;
+
+    let output_fields = OutputFields::new(fields)?;
+    let cinfo = self.get_cinfo();
+
+    utils::with_memstream(w, |fh| unsafe {
+      let mut dumper: raw::json_dumper = std::mem::zeroed();
+
+      dumper.output_file = fh;
+      dumper.flags = raw::JSON_DUMPER_FLAGS_PRETTY_PRINT as i32;
+
+      raw::write_json_proto_tree(
+        output_fields.fields,
+        raw::print_dissections_e_print_dissections_expanded,
+        false,
+        self.get_edt(),
+        cinfo,
+        Some(raw::proto_node_group_children_by_unique),
+        (&mut dumper) as *mut raw::json_dumper,
+      );
+
+      raw::json_dumper_finish((&mut dumper) as *mut raw::json_dumper);
+    })
+  }
+
+  pub fn write_ek<W: Write>(&self, w: &mut W, fields: &[&str]) -> Result<(), Error> {
+    self.check_dissected()?;
+
+    let output_fields = OutputFields::new(fields)?;
+    let cinfo = self.get_cinfo();
+
+    utils::with_memstream(w, |fh| unsafe {
+      raw::write_ek_proto_tree(
+        output_fields.fields,
+        false,
+        false,
+        self.get_edt(),
+        cinfo,
+        fh,
+      );
+    })
+  }
+
+  // Like tshark -V. Only `fields` are written when given, each with its
+  // subtree, as a flat list under the packet.
+  pub fn write_text<W: Write>(&self, w: &mut W, fields: &[&str]) -> Result<(), Error> {
+    self.check_dissected()?;
+
+    if fields.is_empty() {
+      return utils::with_memstream(w, |fh| unsafe {
+        let stream = raw::print_stream_text_stdio_new(fh);
+
+        raw::proto_tree_print(
+          raw::print_dissections_e_print_dissections_expanded,
+          false,
+          self.get_edt(),
+          std::ptr::null_mut(),
+          stream,
+        );
+
+        raw::destroy_print_stream(stream);
+      });
+    }
+
+    for field in fields {
+      let cfield = CString::new(*field).map_err(|_| Error::InvalidField(field.to_string()))?;
+
+      if unsafe { raw::proto_registrar_get_byname(cfield.as_ptr()) }.is_null() {
+        return Err(Error::InvalidField(field.to_string()));
+      }
+    }
+
+    let root = self.get_root_node()?;
+
+    for field in fields {
+      for node in root.iter_depth_first() {
+        if node.get_abbrev() == *field && !node.is_hidden() {
+          write_text_node(w, &node, 0)?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  fn check_dissected(&self) -> Result<(), Error> {
+    match self.root_node {
+      None => Err(Error::NotDissected),
+      Some(_) => Ok(()),
+    }
+  }
+
+  fn get_edt(&self) -> *mut raw::epan_dissect_t {
+    (&mut self.inner_pr.borrow_mut().edt) as *mut raw::epan_dissect_t
+  }
+
+  fn get_cinfo(&self) -> *mut raw::column_info {
+    match self.session.borrow_mut().cinfo {
+      Some(ref mut cinfo) => cinfo.as_mut() as *mut raw::column_info,
+      None => std::ptr::null_mut(),
+    }
+  }
+}
+
+impl Session {
+  pub fn write_pdml_preamble<W: Write>(&self, w: &mut W, filename: &str) -> Result<(), Error> {
+    let cfilename = CString::new(filename).unwrap();
+
+    utils::with_memstream(w, |fh| unsafe {
+      raw::write_pdml_preamble(fh, cfilename.as_ptr());
+    })
+  }
+
+  pub fn write_pdml_finale<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+    utils::with_memstream(w, |fh| unsafe {
+      raw::write_pdml_finale(fh);
+    })
+  }
+
+  pub fn write_psml_preamble<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+    let mut inner = self.epan.borrow_mut();
+
+    let Some(ref mut cinfo) = inner.cinfo else {
+      return Err(Error::NoColumns);
+    };
+
+    utils::with_memstream(w, |fh| unsafe {
+      raw::write_psml_preamble(cinfo.as_mut() as *mut raw::column_info, fh);
+    })
+  }
+
+  pub fn write_psml_finale<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+    utils::with_memstream(w, |fh| unsafe {
+      raw::write_psml_finale(fh);
+    })
+  }
+}
+
+// Indented and bracketed the way proto_tree_print() writes its lines.
+fn write_text_node<W: Write>(w: &mut W, node: &ProtoNode, level: usize) -> Result<(), Error> {
+  if node.is_hidden() {
+    return Ok(());
+  }
+
+  let label = node.get_label().unwrap_or_default();
+
+  if node.is_generated() {
+    writeln!(w, "{:indent$}[{}]", "", label, indent = level * 4)?;
+  } else {
+    writeln!(w, "{:indent$}{}", "", label, indent = level * 4)?;
+  }
+
+  for child in node.iter_children() {
+    write_text_node(w, &child, level + 1)?;
+  }
+
+  Ok(())
+}
+
+struct OutputFields {
+  fields: *mut raw::output_fields_t,
+}
+
+impl OutputFields {
+  fn new(fields: &[&str]) -> Result<OutputFields, Error> {
+    let output_fields = OutputFields {
+      fields: unsafe { raw::output_fields_new() },
+    };
+
+    for field in fields {
+      let cfield = CString::new(*field).map_err(|_| Error::InvalidField(field.to_string()))?;
+
+      unsafe {
+        raw::output_fields_add(output_fields.fields, cfield.as_ptr());
+      }
+    }
+
+    Ok(output_fields)
+  }
+}
+
+impl Drop for OutputFields {
+  fn drop(&mut self) {
+    unsafe {
+      raw::output_fields_free(self.fields);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils;
+
+  // The first query of data/1.pcap, for users.rust-lang.org.
+  fn first_query(session: &Session) -> ParsedRec {
+    let mut wtap =
+      Wtap::new(concat!(env!("CARGO_MANIFEST_DIR"), "/data/1.pcap").to_string()).unwrap();
+    let mut rec = wtap.read().unwrap();
+    let mut prec = session.new_prec(&mut rec);
+
+    prec.dissect();
+
+    prec
+  }
+
+  fn output(f: impl FnOnce(&mut Vec<u8>) -> Result<(), Error>) -> String {
+    let mut out = Vec::new();
+
+    f(&mut out).unwrap();
+
+    String::from_utf8(out).unwrap()
+  }
+
+  #[test]
+  fn pdml() {
+    let _guard = utils::init_for_tests();
+
+    let session = Session::new();
+    let prec = first_query(&session);
+
+    let full = output(|w| prec.write_pdml(w, &[]));
+
+    assert!(full.starts_with("<packet>"));
+    assert!(full.contains("<proto name=\"udp\""));
+    assert!(full.contains("<field name=\"dns.qry.name\""));
+
+    let fields = output(|w| prec.write_pdml(w, &["dns.qry.name"]));
+
+    assert!(fields.contains("<field name=\"dns.qry.name\""));
+    assert!(fields.contains("users.rust-lang.org"));
+    assert!(!fields.contains("<proto name=\"udp\""));
+  }
+
+  #[test]
+  fn psml() {
+    let _guard = utils::init_for_tests();
+
+    let session = Session::new();
+    let prec = first_query(&session);
+
+    assert!(matches!(
+      prec.write_psml(&mut Vec::new()),
+      Err(Error::NoColumns)
+    ));
+
+    session.set_columns(&Column::defaults()).unwrap();
+
+    let prec = first_query(&session);
+    let packet = output(|w| prec.write_psml(w));
+
+    assert!(packet.starts_with("<packet>"));
+    assert!(packet.contains("<section>127.0.0.1</section>"));
+    assert!(packet.contains("<section>DNS</section>"));
+
+    let preamble = output(|w| session.write_psml_preamble(w));
+
+    assert!(preamble.contains("<section>Protocol</section>"));
+  }
+
+  #[test]
+  fn json() {
+    let _guard = utils::init_for_tests();
+
+    let session = Session::new();
+    let prec = first_query(&session);
+
+    let full = output(|w| prec.write_json(w, &[]));
+
+    assert!(full.contains("\"layers\""));
+    assert!(full.contains("\"udp\""));
+    assert!(full.contains("\"dns.qry.name\": \"users.rust-lang.org\""));
+
+    let fields = output(|w| prec.write_json(w, &["dns.qry.name"]));
+
+    assert!(fields.contains("\"dns.qry.name\""));
+    assert!(fields.contains("users.rust-lang.org"));
+    assert!(!fields.contains("\"udp\""));
+  }
+
+  #[test]
+  fn ek() {
+    let _guard = utils::init_for_tests();
+
+    let session = Session::new();
+    let prec = first_query(&session);
+
+    let full = output(|w| prec.write_ek(w, &[]));
+
+    // An index line followed by the packet, one JSON object per line.
+    assert_eq!(full.lines().count(), 2);
+    assert!(full.starts_with("{\"index\""));
+    assert!(full.contains("\"layers\""));
+    assert!(full.contains("users.rust-lang.org"));
+
+    let fields = output(|w| prec.write_ek(w, &["dns.qry.name"]));
+
+    assert!(fields.contains("users.rust-lang.org"));
+    assert!(!fields.contains("\"udp\""));
+  }
+
+  #[test]
+  fn text() {
+    let _guard = utils::init_for_tests();
+
+    let session = Session::new();
+    let prec = first_query(&session);
+
+    let full = output(|w| prec.write_text(w, &[]));
+
+    assert!(full.starts_with("Frame 1:"));
+    assert!(full.contains("\nUser Datagram Protocol, Src Port: "));
+    assert!(full.contains("\n    Queries\n"));
+
+    assert_eq!(
+      output(|w| prec.write_text(w, &["dns.qry.name"])),
+      "Name: users.rust-lang.org\n"
+    );
+
+    assert!(matches!(
+      prec.write_text(&mut Vec::new(), &["dns.no_such_field"]),
+      Err(Error::InvalidField(_))
+    ));
+  }
+}
@@ -0,0 +1,172 @@
+use std::ffi::CString;
+
+use crate::Error;
+use crate::color;
+use crate::raw;
+use crate::utils;
+
+// Sets up the configuration directories, then initializes wiretap and epan
+// and loads settings the way Wireshark does at startup: global then personal
+// preferences, disabled and enabled protocols, heuristic dissector settings,
+// Decode As entries and colouring rules of the selected profile.
+pub fn init_with_profile(
+  personal_dir: Option<String>,
+  profile: Option<String>,
+) -> Result<(), Error> {
+  let arg0 = std::env::current_exe()
+    .ok()
+    .and_then(|p| p.to_str().map(|s| s.to_string()))
+    .or_else(|| std::env::args().next())
+    .unwrap_or_default();
+
+  let carg0 = CString::new(arg0).unwrap();
+
+  unsafe {
+    let errmsg = raw::configuration_init(carg0.as_ptr(), std::ptr::null());
+
+    if !errmsg.is_null() {
+      let msg = utils::cstr_to_string(errmsg);
+      raw::g_free(errmsg as raw::gpointer);
+
+      return Err(Error::Profile(msg));
+    }
+
+    if let Some(dir) = personal_dir {
+      let cdir = CString::new(dir).unwrap();
+
+      raw::set_persconffile_dir(cdir.as_ptr());
+    }
+
+    if let Some(name) = profile {
+      let cname = CString::new(name.clone()).unwrap();
+
+      if !raw::profile_exists(cname.as_ptr(), false) {
+        return Err(Error::Profile(format!("profile '{}' does not exist", name)));
+      }
+
+      raw::set_profile_name(cname.as_ptr());
+    }
+  }
+
+  crate::wtap_init();
+
+  if !crate::epan_init() {
+    return Err(Error::Profile("could not initialize EPAN".to_string()));
+  }
+
+  load_settings()
+}
+
+// Reads the settings of the current profile and applies the preferences to
+// the dissectors. Only needed when epan was initialized without
+// init_with_profile().
+pub fn load_settings() -> Result<(), Error> {
+  unsafe {
+    raw::epan_load_settings();
+    raw::prefs_apply_all();
+  }
+
+  color::init_color_filters()
+}
+
+pub fn get_profile_name() -> String {
+  unsafe { utils::cstr_to_string(raw::get_profile_name()) }
+}
+
+pub fn get_profile_dir() -> String {
+  unsafe {
+    let dir = raw::get_profile_dir(std::ptr::null(), false);
+    let s = utils::cstr_to_string(dir);
+    raw::g_free(dir as raw::gpointer);
+
+    s
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Color, ColorRule, Session, Wtap};
+  use std::fs;
+
+  #[test]
+  fn profile_color_filters() {
+    let _guard = utils::init_for_tests();
+
+    let dir = std::env::temp_dir().join(format!("rust-wireshark-profile-{}", std::process::id()));
+    let profile_dir = dir.join("profiles").join("analyst");
+
+    fs::create_dir_all(&profile_dir).unwrap();
+    fs::write(
+      profile_dir.join("colorfilters"),
+      "@DNS reply@dns.flags.response == 1@[65535,0,0][0,0,65535]\n",
+    )
+    .unwrap();
+
+    let cdir = CString::new(dir.to_string_lossy().into_owned()).unwrap();
+    let cname = CString::new("analyst").unwrap();
+
+    let prev_dir = unsafe {
+      let dir = raw::get_persconffile_dir_no_profile();
+      let prev = CString::new(utils::cstr_to_string(dir)).unwrap();
+      raw::g_free(dir as raw::gpointer);
+
+      raw::set_persconffile_dir(cdir.as_ptr());
+      raw::set_profile_name(cname.as_ptr());
+
+      prev
+    };
+
+    load_settings().unwrap();
+
+    assert_eq!(get_profile_name(), "analyst");
+
+    let session = Session::new();
+    let ctx = session.new_capture_context().unwrap();
+    let mut wtap =
+      Wtap::new(concat!(env!("CARGO_MANIFEST_DIR"), "/data/1.pcap").to_string()).unwrap();
+    let mut rules = Vec::new();
+
+    while let Ok(mut rec) = wtap.read() {
+      let mut prec = ctx.new_prec(&mut rec);
+
+      prec.dissect();
+      rules.push(prec.color_rule().unwrap());
+    }
+
+    unsafe {
+      raw::color_filters_cleanup();
+      raw::set_profile_name(std::ptr::null());
+      raw::set_persconffile_dir(prev_dir.as_ptr());
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+
+    let reply = ColorRule {
+      name: "DNS reply".to_string(),
+      filter: "dns.flags.response == 1".to_string(),
+      foreground: Color {
+        red: 0,
+        green: 0,
+        blue: 65535,
+      },
+      background: Color {
+        red: 65535,
+        green: 0,
+        blue: 0,
+      },
+    };
+
+    assert_eq!(
+      rules,
+      vec![
+        None,
+        Some(reply.clone()),
+        None,
+        None,
+        Some(reply.clone()),
+        Some(reply)
+      ]
+    );
+  }
+}
@@ -1,15 +1,24 @@
 use std::collections::VecDeque;
+use std::ffi::CStr;
 use std::fmt;
 use std::pin::Pin;
 use std::{cell::RefCell, rc::Rc};
 
 use crate::raw;
 use crate::utils;
-use crate::{FValue, Edt};
+use crate::{Edt, FValue, hex_dump};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+  pub start: usize,
+  pub length: usize,
+  // The data source the offsets refer to.
+  pub(crate) tvb: *mut raw::tvbuff_t,
+}
 
 #[derive(Debug)]
 pub struct ProtoNode {
-  prec: Rc<RefCell<Pin<Box<Edt>>>>,
+  pub(crate) prec: Rc<RefCell<Pin<Box<Edt>>>>,
 
   pub(crate) raw_node: *mut raw::proto_node,
 
@@ -56,6 +65,10 @@ impl ProtoNode {
     }
   }
 
+  pub(crate) fn get_edt(&self) -> *mut raw::epan_dissect_t {
+    (&mut self.prec.borrow_mut().edt) as *mut raw::epan_dissect_t
+  }
+
   pub fn get_abbrev(&self) -> String {
     unsafe {
       let hfinfo = (*self.raw_node).hfinfo;
@@ -76,6 +89,95 @@ impl ProtoNode {
     }
   }
 
+  // None for nodes that don't map to packet bytes, such as the tree root.
+  pub fn byte_range(&self) -> Option<ByteRange> {
+    unsafe {
+      let finfo = (*self.raw_node).finfo;
+
+      if finfo.is_null() || (*finfo).ds_tvb.is_null() || (*finfo).start < 0 {
+        None
+      } else {
+        Some(ByteRange {
+          start: (*finfo).start as usize,
+          length: (*finfo).length.max(0) as usize,
+          tvb: (*finfo).ds_tvb,
+        })
+      }
+    }
+  }
+
+  // Truncated to what was captured.
+  pub fn bytes(&self) -> Option<Vec<u8>> {
+    let range = self.byte_range()?;
+
+    Some(tvb_bytes(range.tvb, range.start, range.length))
+  }
+
+  // Dumps the whole data source of this node with its bytes highlighted.
+  pub fn hex_dump(&self) -> Option<String> {
+    let range = self.byte_range()?;
+
+    let data = tvb_bytes(range.tvb, 0, usize::MAX);
+
+    Some(hex_dump(
+      &data,
+      Some(range.start..range.start + range.length),
+    ))
+  }
+
+  // The text Wireshark shows for this node in the packet details pane.
+  pub fn get_label(&self) -> Option<String> {
+    unsafe {
+      let finfo = (*self.raw_node).finfo;
+
+      if finfo.is_null() {
+        return None;
+      }
+
+      if !(*finfo).rep.is_null() {
+        let label = CStr::from_ptr((*(*finfo).rep).representation.as_ptr());
+
+        return Some(label.to_string_lossy().into_owned());
+      }
+
+      let mut label = [0 as raw::gchar; raw::ITEM_LABEL_LENGTH as usize];
+
+      raw::proto_item_fill_label(finfo, label.as_mut_ptr(), std::ptr::null_mut());
+
+      Some(
+        CStr::from_ptr(label.as_ptr())
+          .to_string_lossy()
+          .into_owned(),
+      )
+    }
+  }
+
+  pub(crate) fn is_protocol(&self) -> bool {
+    unsafe {
+      let hfinfo = (*self.raw_node).hfinfo;
+
+      !hfinfo.is_null() && (*hfinfo).type_ == raw::ftenum_FT_PROTOCOL
+    }
+  }
+
+  pub fn is_hidden(&self) -> bool {
+    unsafe {
+      let finfo = (*self.raw_node).finfo;
+
+      !finfo.is_null() && ((*finfo).flags & raw::FI_HIDDEN) != 0
+    }
+  }
+
+  // Shown in brackets: the value was derived rather than read from the
+  // packet.
+  pub fn is_generated(&self) -> bool {
+    unsafe {
+      let finfo = (*self.raw_node).finfo;
+
+      !finfo.is_null() && ((*finfo).flags & raw::FI_GENERATED) != 0
+    }
+  }
+
   pub fn get_parent(&self) -> Option<ProtoNode> {
     let parent = unsafe { (*self.raw_node).parent };
 
@@ -190,6 +292,28 @@ impl ProtoNode {
   }
 }
 
+pub(crate) fn tvb_bytes(tvb: *mut raw::tvbuff_t, start: usize, length: usize) -> Vec<u8> {
+  unsafe {
+    let captured = raw::tvb_captured_length(tvb) as usize;
+
+    if start >= captured {
+      return Vec::new();
+    }
+
+    // tvb_get_ptr() throws on out of bounds access, so never ask for more
+    // than is there.
+    let length = length.min(captured - start);
+
+    let ptr = raw::tvb_get_ptr(tvb, start as i32, length as i32);
+
+    if ptr.is_null() {
+      Vec::new()
+    } else {
+      std::slice::from_raw_parts(ptr, length).to_vec()
+    }
+  }
+}
+
 pub struct ProtoNodeDepthFirstIter {
   parent_node: ProtoNode,
   current_node: Option<ProtoNode>,
@@ -0,0 +1,161 @@
+use std::ffi::CString;
+use std::os::raw::c_void;
+
+use crate::Error;
+use crate::raw;
+use crate::utils;
+
+#[derive(Debug, Clone)]
+pub struct ProtocolInfo {
+  pub id: i32,
+  pub short_name: String,
+  pub long_name: String,
+  pub filter_name: String,
+  pub enabled: bool,
+  pub can_toggle: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct HeuristicInfo {
+  pub table: String,
+  pub short_name: String,
+  pub display_name: Option<String>,
+  pub protocol: Option<String>,
+  pub enabled: bool,
+  pub enabled_by_default: bool,
+}
+
+// Like Decode As rules, these settings are global and affect every session.
+pub struct Protocols;
+
+impl Protocols {
+  pub fn list() -> Vec<ProtocolInfo> {
+    let mut protocols = Vec::new();
+    let mut cookie: *mut c_void = std::ptr::null_mut();
+
+    unsafe {
+      let mut id = raw::proto_get_first_protocol((&mut cookie) as *mut *mut c_void);
+
+      while id != -1 {
+        let protocol = raw::find_protocol_by_id(id);
+
+        if !protocol.is_null() {
+          protocols.push(ProtocolInfo {
+            id,
+            short_name: utils::cstr_to_string(raw::proto_get_protocol_short_name(protocol)),
+            long_name: utils::cstr_to_string(raw::proto_get_protocol_long_name(protocol)),
+            filter_name: utils::cstr_to_string(raw::proto_get_protocol_filter_name(id)),
+            enabled: raw::proto_is_protocol_enabled(protocol),
+            can_toggle: raw::proto_can_toggle_protocol(id),
+          });
+        }
+
+        id = raw::proto_get_next_protocol((&mut cookie) as *mut *mut c_void);
+      }
+    }
+
+    protocols
+  }
+
+  pub fn is_enabled(filter_name: &str) -> Result<bool, Error> {
+    let id = Protocols::find(filter_name)?;
+
+    unsafe { Ok(raw::proto_is_protocol_enabled(raw::find_protocol_by_id(id))) }
+  }
+
+  pub fn set_enabled(filter_name: &str, enabled: bool) -> Result<(), Error> {
+    let id = Protocols::find(filter_name)?;
+
+    unsafe {
+      if !raw::proto_can_toggle_protocol(id) {
+        return Err(Error::Protocol(format!(
+          "{}: cannot be disabled",
+          filter_name
+        )));
+      }
+
+      raw::proto_set_decoding(id, enabled);
+    }
+
+    Ok(())
+  }
+
+  pub fn heuristics() -> Vec<HeuristicInfo> {
+    let mut heuristics: Vec<HeuristicInfo> = Vec::new();
+
+    unsafe {
+      raw::dissector_all_heur_tables_foreach_table(
+        Some(collect_heur_table),
+        (&mut heuristics) as *mut Vec<HeuristicInfo> as *mut c_void,
+        None,
+      );
+    }
+
+    heuristics
+  }
+
+  // Takes the unique short name, such as "rtp_udp". A heuristic whose
+  // protocol is disabled stays inactive even when enabled.
+  pub fn set_heuristic_enabled(short_name: &str, enabled: bool) -> Result<(), Error> {
+    let not_found = || Error::Protocol(format!("no such heuristic dissector: {}", short_name));
+
+    let cname = CString::new(short_name).map_err(|_| not_found())?;
+
+    unsafe {
+      let entry = raw::find_heur_dissector_by_unique_short_name(cname.as_ptr());
+
+      if entry.is_null() {
+        return Err(not_found());
+      }
+
+      (*entry).enabled = enabled;
+    }
+
+    Ok(())
+  }
+
+  fn find(filter_name: &str) -> Result<i32, Error> {
+    let not_found = || Error::Protocol(format!("no such protocol: {}", filter_name));
+
+    let cname = CString::new(filter_name).map_err(|_| not_found())?;
+
+    let id = unsafe { raw::proto_get_id_by_filter_name(cname.as_ptr()) };
+
+    if id == -1 { Err(not_found()) } else { Ok(id) }
+  }
+}
+
+unsafe extern "C" fn collect_heur_table(
+  table_name: *const raw::gchar,
+  _table: *mut raw::heur_dissector_list,
+  user_data: raw::gpointer,
+) {
+  unsafe {
+    raw::heur_dissector_table_foreach(table_name, Some(collect_heur), user_data);
+  }
+}
+
+unsafe extern "C" fn collect_heur(
+  table_name: *const raw::gchar,
+  entry: *mut raw::heur_dtbl_entry,
+  user_data: raw::gpointer,
+) {
+  unsafe {
+    let heuristics = &mut *(user_data as *mut Vec<HeuristicInfo>);
+
+    let protocol = if (*entry).protocol.is_null() {
+      None
+    } else {
+      utils::opt_cstr_to_string(raw::proto_get_protocol_short_name((*entry).protocol))
+    };
+
+    heuristics.push(HeuristicInfo {
+      table: utils::cstr_to_string(table_name),
+      short_name: utils::cstr_to_string((*entry).short_name),
+      display_name: utils::opt_cstr_to_string((*entry).display_name),
+      protocol,
+      enabled: (*entry).enabled,
+      enabled_by_default: (*entry).enabled_by_default,
+    });
+  }
+}
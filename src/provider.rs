@@ -0,0 +1,484 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fmt;
+use std::os::raw::{c_char, c_uint};
+use std::rc::Rc;
+
+use crate::context::InnerCaptureContext;
+use crate::utils;
+use crate::{Error, raw};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NsTime {
+  pub secs: i64,
+  pub nsecs: i32,
+}
+
+impl NsTime {
+  pub fn as_secs_f64(&self) -> f64 {
+    self.secs as f64 + self.nsecs as f64 / 1e9
+  }
+}
+
+impl From<raw::nstime_t> for NsTime {
+  fn from(ts: raw::nstime_t) -> Self {
+    NsTime {
+      secs: ts.secs as i64,
+      nsecs: ts.nsecs,
+    }
+  }
+}
+
+impl From<NsTime> for raw::nstime_t {
+  fn from(ts: NsTime) -> Self {
+    raw::nstime_t {
+      secs: ts.secs as raw::time_t,
+      nsecs: ts.nsecs,
+    }
+  }
+}
+
+// A packet block carrying edits made to a frame, such as added comments,
+// which the frame dissector shows instead of the record's own block.
+pub struct PacketBlock {
+  block: raw::wtap_block_t,
+}
+
+impl Drop for PacketBlock {
+  fn drop(&mut self) {
+    unsafe {
+      raw::wtap_block_unref(self.block);
+    }
+  }
+}
+
+impl fmt::Debug for PacketBlock {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "PacketBlock {{ comments: {:?} }}", self.get_comments())
+  }
+}
+
+impl PacketBlock {
+  pub fn new() -> PacketBlock {
+    PacketBlock {
+      block: unsafe { raw::wtap_block_create(raw::wtap_block_type_t_WTAP_BLOCK_PACKET) },
+    }
+  }
+
+  pub fn add_comment(&mut self, comment: &str) -> Result<(), Error> {
+    let ccomment = CString::new(comment)
+      .map_err(|_| Error::InvalidField("comment contains a NUL byte".to_string()))?;
+
+    let ret = unsafe {
+      raw::wtap_block_add_string_option(
+        self.block,
+        raw::OPT_COMMENT,
+        ccomment.as_ptr(),
+        comment.len(),
+      )
+    };
+
+    if ret == raw::wtap_opttype_return_val_WTAP_OPTTYPE_SUCCESS {
+      Ok(())
+    } else {
+      Err(Error::InvalidField(format!("cannot add comment ({})", ret)))
+    }
+  }
+
+  pub fn get_comments(&self) -> Vec<String> {
+    let mut comments = Vec::new();
+
+    loop {
+      let mut value: *mut c_char = std::ptr::null_mut();
+
+      let ret = unsafe {
+        raw::wtap_block_get_nth_string_option_value(
+          self.block,
+          raw::OPT_COMMENT,
+          comments.len() as u32,
+          (&mut value) as *mut *mut c_char,
+        )
+      };
+
+      if ret != raw::wtap_opttype_return_val_WTAP_OPTTYPE_SUCCESS {
+        return comments;
+      }
+
+      comments.push(utils::cstr_to_string(value));
+    }
+  }
+}
+
+impl Default for PacketBlock {
+  fn default() -> Self {
+    PacketBlock::new()
+  }
+}
+
+// Answers the questions epan asks about a capture as a whole while
+// dissecting a single packet: timestamps of other frames (for time
+// references and deltas), interface and process information from the
+// capture file's metadata, and edited blocks.
+pub trait PacketProvider {
+  // Only asked for frames that aren't in the CaptureContext being dissected.
+  fn frame_ts(&self, _frame_num: u32) -> Option<NsTime> {
+    None
+  }
+
+  fn interface_name(&self, _interface_id: u32, _section_number: u32) -> Option<String> {
+    None
+  }
+
+  fn interface_description(&self, _interface_id: u32, _section_number: u32) -> Option<String> {
+    None
+  }
+
+  // Asked once per frame of a CaptureContext, when it is first dissected.
+  fn modified_block(&self, _frame_num: u32) -> Option<PacketBlock> {
+    None
+  }
+
+  fn process_id(&self, _process_info_id: u32, _section_number: u32) -> Option<i32> {
+    None
+  }
+
+  fn process_name(&self, _process_info_id: u32, _section_number: u32) -> Option<String> {
+    None
+  }
+
+  fn process_uuid(&self, _process_info_id: u32, _section_number: u32) -> Option<Vec<u8>> {
+    None
+  }
+
+  fn start_ts(&self) -> Option<NsTime> {
+    None
+  }
+}
+
+// Used by sessions created without a provider.
+struct NoProvider;
+
+impl PacketProvider for NoProvider {}
+
+#[derive(Hash, PartialEq, Eq)]
+enum StringKey {
+  InterfaceName(u32, u32),
+  InterfaceDescription(u32, u32),
+  ProcessName(u32, u32),
+}
+
+// Passed to epan as the opaque packet_provider_data. Everything handed back
+// to C is cached here so that the pointers stay valid after the callback
+// returns. Frame timestamps come from the capture context being dissected,
+// as tshark's provider takes them from its own frame list, and only then
+// from the PacketProvider.
+pub(crate) struct ProviderData {
+  provider: Box<dyn PacketProvider>,
+  ctx: RefCell<Option<Rc<RefCell<InnerCaptureContext>>>>,
+  frame_ts: RefCell<HashMap<u32, Box<raw::nstime_t>>>,
+  blocks: RefCell<HashMap<u32, PacketBlock>>,
+  start_ts: RefCell<Option<Box<raw::nstime_t>>>,
+  strings: RefCell<HashMap<StringKey, CString>>,
+  uuids: RefCell<HashMap<(u32, u32), Vec<u8>>>,
+}
+
+impl fmt::Debug for ProviderData {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "ProviderData")
+  }
+}
+
+impl ProviderData {
+  pub(crate) fn new(provider: Option<Box<dyn PacketProvider>>) -> ProviderData {
+    ProviderData {
+      provider: provider.unwrap_or_else(|| Box::new(NoProvider)),
+      ctx: RefCell::new(None),
+      frame_ts: RefCell::new(HashMap::new()),
+      blocks: RefCell::new(HashMap::new()),
+      start_ts: RefCell::new(None),
+      strings: RefCell::new(HashMap::new()),
+      uuids: RefCell::new(HashMap::new()),
+    }
+  }
+
+  pub(crate) fn funcs() -> raw::packet_provider_funcs {
+    raw::packet_provider_funcs {
+      get_frame_ts: Some(get_frame_ts),
+      get_interface_name: Some(get_interface_name),
+      get_interface_description: Some(get_interface_description),
+      get_modified_block: Some(get_modified_block),
+      get_process_id: Some(get_process_id),
+      get_process_name: Some(get_process_name),
+      get_process_uuid: Some(get_process_uuid),
+      get_start_ts: Some(get_start_ts),
+    }
+  }
+
+  // `ctx` must not be borrowed while `f` dissects, since get_frame_ts
+  // borrows it.
+  pub(crate) fn with_context<R>(
+    &self,
+    ctx: &Rc<RefCell<InnerCaptureContext>>,
+    f: impl FnOnce() -> R,
+  ) -> R {
+    let prev = self.ctx.replace(Some(ctx.clone()));
+    let ret = f();
+
+    self.ctx.replace(prev);
+
+    ret
+  }
+
+  // Frame numbers start over with a new capture context, so answers cached
+  // for the previous frames no longer apply.
+  pub(crate) fn clear_frame_cache(&self) {
+    self.frame_ts.borrow_mut().clear();
+    self.blocks.borrow_mut().clear();
+  }
+
+  // Fetches the frame's edited block, if any, for get_modified_block; epan
+  // only asks for frames that say they have one.
+  pub(crate) fn load_modified_block(&self, frame_num: u32) -> bool {
+    let mut blocks = self.blocks.borrow_mut();
+
+    if !blocks.contains_key(&frame_num) {
+      match self.provider.modified_block(frame_num) {
+        Some(block) => {
+          blocks.insert(frame_num, block);
+        }
+        None => return false,
+      }
+    }
+
+    true
+  }
+
+  fn context_frame_ts(&self, frame_num: u32) -> Option<*const raw::nstime_t> {
+    self.ctx.borrow().as_ref()?.borrow().get_frame_ts(frame_num)
+  }
+
+  fn cache_string(&self, key: StringKey, value: Option<String>) -> *const c_char {
+    let Some(value) = value.and_then(|v| CString::new(v).ok()) else {
+      return std::ptr::null();
+    };
+
+    let mut strings = self.strings.borrow_mut();
+    let cached = strings.entry(key).or_insert(value);
+
+    cached.as_ptr()
+  }
+}
+
+unsafe fn provider_data<'a>(prov: *mut raw::packet_provider_data) -> &'a ProviderData {
+  unsafe { &*(prov as *const ProviderData) }
+}
+
+unsafe extern "C" fn get_frame_ts(
+  prov: *mut raw::packet_provider_data,
+  frame_num: u32,
+) -> *const raw::nstime_t {
+  let data = unsafe { provider_data(prov) };
+
+  if let Some(ts) = data.context_frame_ts(frame_num) {
+    return ts;
+  }
+
+  let mut cache = data.frame_ts.borrow_mut();
+
+  if let Some(ts) = cache.get(&frame_num) {
+    return ts.as_ref() as *const raw::nstime_t;
+  }
+
+  match data.provider.frame_ts(frame_num) {
+    Some(ts) => {
+      let ts = cache.entry(frame_num).or_insert(Box::new(ts.into()));
+
+      ts.as_ref() as *const raw::nstime_t
+    }
+    None => std::ptr::null(),
+  }
+}
+
+unsafe extern "C" fn get_start_ts(prov: *mut raw::packet_provider_data) -> *const raw::nstime_t {
+  let data = unsafe { provider_data(prov) };
+  let mut cache = data.start_ts.borrow_mut();
+
+  if cache.is_none() {
+    *cache = data.provider.start_ts().map(|ts| Box::new(ts.into()));
+  }
+
+  match cache.as_ref() {
+    Some(ts) => ts.as_ref() as *const raw::nstime_t,
+    None => std::ptr::null(),
+  }
+}
+
+unsafe extern "C" fn get_interface_name(
+  prov: *mut raw::packet_provider_data,
+  interface_id: u32,
+  section_number: c_uint,
+) -> *const c_char {
+  let data = unsafe { provider_data(prov) };
+
+  data.cache_string(
+    StringKey::InterfaceName(interface_id, section_number),
+    data.provider.interface_name(interface_id, section_number),
+  )
+}
+
+unsafe extern "C" fn get_interface_description(
+  prov: *mut raw::packet_provider_data,
+  interface_id: u32,
+  section_number: c_uint,
+) -> *const c_char {
+  let data = unsafe { provider_data(prov) };
+
+  data.cache_string(
+    StringKey::InterfaceDescription(interface_id, section_number),
+    data
+      .provider
+      .interface_description(interface_id, section_number),
+  )
+}
+
+unsafe extern "C" fn get_modified_block(
+  prov: *mut raw::packet_provider_data,
+  fd: *const raw::frame_data,
+) -> raw::wtap_block_t {
+  let data = unsafe { provider_data(prov) };
+
+  if fd.is_null() {
+    return std::ptr::null_mut();
+  }
+
+  data
+    .blocks
+    .borrow()
+    .get(&unsafe { (*fd).num })
+    .map_or(std::ptr::null_mut(), |block| block.block)
+}
+
+unsafe extern "C" fn get_process_id(
+  prov: *mut raw::packet_provider_data,
+  process_info_id: u32,
+  section_number: c_uint,
+) -> i32 {
+  let data = unsafe { provider_data(prov) };
+
+  data
+    .provider
+    .process_id(process_info_id, section_number)
+    .unwrap_or(-1)
+}
+
+unsafe extern "C" fn get_process_name(
+  prov: *mut raw::packet_provider_data,
+  process_info_id: u32,
+  section_number: c_uint,
+) -> *const c_char {
+  let data = unsafe { provider_data(prov) };
+
+  data.cache_string(
+    StringKey::ProcessName(process_info_id, section_number),
+    data.provider.process_name(process_info_id, section_number),
+  )
+}
+
+unsafe extern "C" fn get_process_uuid(
+  prov: *mut raw::packet_provider_data,
+  process_info_id: u32,
+  section_number: c_uint,
+  uuid_size: *mut usize,
+) -> *const u8 {
+  let data = unsafe { provider_data(prov) };
+  let mut uuids = data.uuids.borrow_mut();
+  let key = (process_info_id, section_number);
+
+  if !uuids.contains_key(&key) {
+    match data.provider.process_uuid(process_info_id, section_number) {
+      Some(uuid) => {
+        uuids.insert(key, uuid);
+      }
+      None => return std::ptr::null(),
+    }
+  }
+
+  let uuid = &uuids[&key];
+
+  if !uuid_size.is_null() {
+    unsafe {
+      *uuid_size = uuid.len();
+    }
+  }
+
+  uuid.as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Session, Wtap};
+
+  struct Comments;
+
+  impl PacketProvider for Comments {
+    fn modified_block(&self, frame_num: u32) -> Option<PacketBlock> {
+      if frame_num != 2 {
+        return None;
+      }
+
+      let mut block = PacketBlock::new();
+
+      block.add_comment("edited").unwrap();
+
+      Some(block)
+    }
+  }
+
+  #[test]
+  fn modified_block_comments() {
+    let _guard = utils::init_for_tests();
+
+    let session = Session::with_provider(Box::new(Comments));
+    let ctx = session.new_capture_context().unwrap();
+    let mut wtap =
+      Wtap::new(concat!(env!("CARGO_MANIFEST_DIR"), "/data/1.pcap").to_string()).unwrap();
+    let mut comments = Vec::new();
+
+    while let Ok(mut rec) = wtap.read() {
+      let mut prec = ctx.new_prec(&mut rec);
+
+      prec.dissect();
+
+      let comment = prec
+        .get_root_node()
+        .unwrap()
+        .find_hierarchical("frame.comment".to_string())
+        .and_then(|node| node.get_fvalue())
+        .and_then(|fv| fv.get_string());
+
+      comments.push(comment);
+    }
+
+    assert_eq!(
+      comments,
+      vec![None, Some("edited".to_string()), None, None, None, None]
+    );
+  }
+
+  #[test]
+  fn packet_block() {
+    let _guard = utils::init_for_tests();
+
+    let mut block = PacketBlock::new();
+
+    assert!(block.get_comments().is_empty());
+
+    block.add_comment("one").unwrap();
+    block.add_comment("two").unwrap();
+
+    assert_eq!(block.get_comments(), vec!["one", "two"]);
+    assert!(block.add_comment("a\0b").is_err());
+  }
+}
@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+use std::ffi::CString;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::error::WsError;
+use crate::utils;
+use crate::*;
+
+#[derive(Debug, Clone)]
+pub struct RingBufferOptions {
+  pub path: PathBuf,
+  pub filesize: Option<u64>,
+  pub duration: Option<Duration>,
+  pub packets: Option<u64>,
+  pub files: Option<usize>,
+}
+
+impl RingBufferOptions {
+  pub fn new(path: PathBuf) -> RingBufferOptions {
+    RingBufferOptions {
+      path,
+      filesize: None,
+      duration: None,
+      packets: None,
+      files: None,
+    }
+  }
+}
+
+// Writes records to a sequence of pcapng files named like dumpcap's,
+// <stem>_<index>_<YYYYmmddHHMMSS>.<ext> in local time, keeping at most
+// `files` of them.
+pub(crate) struct RingBuffer {
+  options: RingBufferOptions,
+  encap: i32,
+  snaplen: i32,
+  dumper: *mut raw::wtap_dumper,
+  files: VecDeque<PathBuf>,
+  files_created: usize,
+  file_started: Instant,
+  file_packets: u64,
+}
+
+impl Drop for RingBuffer {
+  fn drop(&mut self) {
+    let _ = self.close_file();
+  }
+}
+
+impl RingBuffer {
+  pub(crate) fn new(
+    options: RingBufferOptions,
+    encap: i32,
+    snaplen: i32,
+  ) -> Result<RingBuffer, Error> {
+    let mut ring = RingBuffer {
+      options,
+      encap,
+      snaplen,
+      dumper: std::ptr::null_mut(),
+      files: VecDeque::new(),
+      files_created: 0,
+      file_started: Instant::now(),
+      file_packets: 0,
+    };
+
+    ring.open_file()?;
+
+    Ok(ring)
+  }
+
+  pub(crate) fn get_current_file(&self) -> Option<&PathBuf> {
+    self.files.back()
+  }
+
+  pub(crate) fn get_files_created(&self) -> usize {
+    self.files_created
+  }
+
+  pub(crate) fn get_bytes_written(&self) -> u64 {
+    if self.dumper.is_null() {
+      0
+    } else {
+      unsafe { raw::wtap_get_bytes_dumped(self.dumper) as u64 }
+    }
+  }
+
+  pub(crate) fn should_switch(&self) -> bool {
+    self
+      .options
+      .filesize
+      .is_some_and(|size| self.get_bytes_written() >= size)
+      || self
+        .options
+        .duration
+        .is_some_and(|duration| self.file_started.elapsed() >= duration)
+      || self
+        .options
+        .packets
+        .is_some_and(|packets| self.file_packets >= packets)
+  }
+
+  pub(crate) fn switch_file(&mut self) -> Result<PathBuf, Error> {
+    self.close_file()?;
+    self.open_file()
+  }
+
+  pub(crate) fn write(&mut self, rec: &WtapRec) -> Result<(), Error> {
+    let mut err = WsError::new();
+
+    unsafe {
+      let ret = raw::wtap_dump(
+        self.dumper,
+        (&rec.rec.borrow().rec) as *const raw::wtap_rec,
+        (&mut err.err) as *mut ::std::os::raw::c_int,
+        (&mut err.errInfo) as *mut *mut raw::gchar,
+      );
+
+      if !ret {
+        return Err(Error::WsError(err));
+      }
+    }
+
+    self.file_packets += 1;
+
+    Ok(())
+  }
+
+  fn next_path(&self) -> PathBuf {
+    let stem = self
+      .options
+      .path
+      .file_stem()
+      .map(|s| s.to_string_lossy().into_owned())
+      .unwrap_or_else(|| "capture".to_string());
+
+    let ext = self
+      .options
+      .path
+      .extension()
+      .map(|s| s.to_string_lossy().into_owned())
+      .unwrap_or_else(|| "pcapng".to_string());
+
+    let now = unsafe {
+      let format = CString::new("%Y%m%d%H%M%S").unwrap();
+      let dt = raw::g_date_time_new_now_local();
+      let formatted = raw::g_date_time_format(dt, format.as_ptr());
+      let now = utils::cstr_to_string(formatted);
+
+      raw::g_free(formatted as raw::gpointer);
+      raw::g_date_time_unref(dt);
+
+      now
+    };
+
+    self.options.path.with_file_name(format!(
+      "{}_{:05}_{}.{}",
+      stem,
+      self.files_created + 1,
+      now,
+      ext
+    ))
+  }
+
+  fn open_file(&mut self) -> Result<PathBuf, Error> {
+    let path = self.next_path();
+    let cpath = CString::new(path.to_string_lossy().into_owned()).unwrap();
+
+    let mut err = WsError::new();
+
+    unsafe {
+      let mut params: raw::wtap_dump_params = std::mem::zeroed();
+
+      raw::wtap_dump_params_init(
+        (&mut params) as *mut raw::wtap_dump_params,
+        std::ptr::null_mut(),
+      );
+
+      params.encap = self.encap;
+      params.snaplen = self.snaplen;
+      params.tsprec = raw::WTAP_TSPREC_USEC as i32;
+
+      let dumper = raw::wtap_dump_open(
+        cpath.as_ptr(),
+        raw::wtap_pcapng_file_type_subtype(),
+        raw::ws_compression_type_WS_FILE_UNCOMPRESSED,
+        (&params) as *const raw::wtap_dump_params,
+        (&mut err.err) as *mut ::std::os::raw::c_int,
+        (&mut err.errInfo) as *mut *mut raw::gchar,
+      );
+
+      if dumper.is_null() {
+        return Err(Error::WsError(err));
+      }
+
+      self.dumper = dumper;
+    }
+
+    self.files.push_back(path.clone());
+    self.files_created += 1;
+    self.file_started = Instant::now();
+    self.file_packets = 0;
+
+    if let Some(max) = self.options.files {
+      while self.files.len() > max.max(1) {
+        if let Some(oldest) = self.files.pop_front() {
+          let _ = fs::remove_file(oldest);
+        }
+      }
+    }
+
+    Ok(path)
+  }
+
+  fn close_file(&mut self) -> Result<(), Error> {
+    if self.dumper.is_null() {
+      return Ok(());
+    }
+
+    let mut err = WsError::new();
+    let mut needs_reload = false;
+
+    let ret = unsafe {
+      raw::wtap_dump_close(
+        self.dumper,
+        (&mut needs_reload) as *mut bool,
+        (&mut err.err) as *mut ::std::os::raw::c_int,
+        (&mut err.errInfo) as *mut *mut raw::gchar,
+      )
+    };
+
+    self.dumper = std::ptr::null_mut();
+
+    if ret {
+      Ok(())
+    } else {
+      Err(Error::WsError(err))
+    }
+  }
+}
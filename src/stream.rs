@@ -0,0 +1,298 @@
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::utils;
+use crate::*;
+
+#[derive(Debug, Clone)]
+pub struct PcapStreamOptions {
+  pub reconnect: bool,
+  pub reconnect_delay: Duration,
+  // Limits reconnection attempts, whether or not they succeed.
+  pub max_reconnects: Option<usize>,
+}
+
+impl Default for PcapStreamOptions {
+  fn default() -> Self {
+    PcapStreamOptions {
+      reconnect: true,
+      reconnect_delay: Duration::from_secs(1),
+      max_reconnects: None,
+    }
+  }
+}
+
+enum Endpoint {
+  Connect(String),
+  Listen(TcpListener),
+}
+
+// One connection's worth of pcap data: a thread copies the socket into a
+// FIFO which wiretap reads like any other pipe.
+struct Connection {
+  wtap: Option<Wtap>,
+  socket: TcpStream,
+  fifo: PathBuf,
+  copier: Option<JoinHandle<()>>,
+}
+
+impl Drop for Connection {
+  fn drop(&mut self) {
+    self.wtap.take();
+
+    let _ = self.socket.shutdown(Shutdown::Both);
+
+    // A copier still waiting for wiretap to open the FIFO is released by a
+    // non-blocking reader, whose close then fails its writes.
+    if let Some(copier) = self.copier.take() {
+      if !copier.is_finished() {
+        let _ = OpenOptions::new()
+          .read(true)
+          .custom_flags(raw::O_NONBLOCK as i32)
+          .open(&self.fifo);
+      }
+
+      let _ = copier.join();
+    }
+
+    let _ = fs::remove_file(&self.fifo);
+  }
+}
+
+impl Connection {
+  fn new(socket: TcpStream) -> Result<Connection, Error> {
+    let fifo = utils::make_fifo("stream")?;
+
+    let mut conn = Connection {
+      wtap: None,
+      socket: socket.try_clone()?,
+      fifo: fifo.clone(),
+      copier: None,
+    };
+
+    conn.copier = Some(std::thread::spawn(move || {
+      let mut socket = socket;
+
+      if let Ok(mut out) = OpenOptions::new().write(true).open(&fifo) {
+        let _ = io::copy(&mut socket, &mut out);
+      }
+    }));
+
+    let path = conn.fifo.to_string_lossy().into_owned();
+
+    // On failure, dropping conn stops and joins the copier.
+    conn.wtap = Some(Wtap::new(path).map_err(Error::WsError)?);
+
+    Ok(conn)
+  }
+}
+
+pub struct PcapStream {
+  endpoint: Endpoint,
+  options: PcapStreamOptions,
+  conn: Option<Connection>,
+  connections: usize,
+  attempts: usize,
+  reconnects: usize,
+  peer: Option<SocketAddr>,
+}
+
+impl PcapStream {
+  pub fn connect(addr: String, options: PcapStreamOptions) -> Result<PcapStream, Error> {
+    let mut stream = PcapStream {
+      endpoint: Endpoint::Connect(addr),
+      options,
+      conn: None,
+      connections: 0,
+      attempts: 0,
+      reconnects: 0,
+      peer: None,
+    };
+
+    stream.open_next()?;
+
+    Ok(stream)
+  }
+
+  pub fn listen(addr: String, options: PcapStreamOptions) -> Result<PcapStream, Error> {
+    Ok(PcapStream {
+      endpoint: Endpoint::Listen(TcpListener::bind(addr)?),
+      options,
+      conn: None,
+      connections: 0,
+      attempts: 0,
+      reconnects: 0,
+      peer: None,
+    })
+  }
+
+  pub fn local_addr(&self) -> Option<SocketAddr> {
+    match &self.endpoint {
+      Endpoint::Listen(listener) => listener.local_addr().ok(),
+      Endpoint::Connect(_) => self.conn.as_ref().and_then(|c| c.socket.local_addr().ok()),
+    }
+  }
+
+  pub fn peer_addr(&self) -> Option<SocketAddr> {
+    self.peer
+  }
+
+  // Successful reconnections only.
+  pub fn get_reconnects(&self) -> usize {
+    self.reconnects
+  }
+
+  // Returns Error::EOF once a stream has ended and no further connection
+  // will be made.
+  pub fn read(&mut self) -> Result<WtapRec, Error> {
+    loop {
+      if self.conn.is_none() {
+        self.open_next()?;
+      }
+
+      let wtap = self.conn.as_mut().and_then(|c| c.wtap.as_mut()).unwrap();
+
+      match wtap.read() {
+        Ok(rec) => return Ok(rec),
+        // A sender that goes away mid-record leaves a short read behind,
+        // which is just another way for the stream to end.
+        Err(Error::EOF) | Err(Error::WsError(_)) if self.options.reconnect => {
+          self.conn = None;
+        }
+        Err(e) => return Err(e),
+      }
+    }
+  }
+
+  fn open_next(&mut self) -> Result<(), Error> {
+    let is_reconnect = self.connections > 0;
+
+    loop {
+      if is_reconnect {
+        if !self.options.reconnect
+          || self
+            .options
+            .max_reconnects
+            .is_some_and(|max| self.attempts >= max)
+        {
+          return Err(Error::EOF);
+        }
+
+        self.attempts += 1;
+      }
+
+      let socket = match &self.endpoint {
+        Endpoint::Listen(listener) => listener.accept().map(|(socket, _)| socket),
+        Endpoint::Connect(addr) => TcpStream::connect(addr),
+      };
+
+      let result = socket.map_err(Error::IoError).and_then(|socket| {
+        self.peer = socket.peer_addr().ok();
+        Connection::new(socket)
+      });
+
+      match result {
+        Ok(conn) => {
+          self.conn = Some(conn);
+          self.connections += 1;
+
+          if is_reconnect {
+            self.reconnects += 1;
+          }
+
+          return Ok(());
+        }
+        Err(e) if !is_reconnect => return Err(e),
+        Err(_) => std::thread::sleep(self.options.reconnect_delay),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Write;
+
+  fn pcap_bytes() -> Vec<u8> {
+    fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/data/1.pcap")).unwrap()
+  }
+
+  #[test]
+  fn listen_with_reconnect() {
+    let _guard = utils::init_for_tests();
+
+    let options = PcapStreamOptions {
+      reconnect_delay: Duration::from_millis(10),
+      max_reconnects: Some(1),
+      ..PcapStreamOptions::default()
+    };
+
+    let mut stream = PcapStream::listen("127.0.0.1:0".to_string(), options).unwrap();
+    let addr = stream.local_addr().unwrap();
+
+    // Stands in for `tcpdump -w - | nc`, sending the capture twice.
+    let sender = std::thread::spawn(move || {
+      for _ in 0..2 {
+        let mut socket = TcpStream::connect(addr).unwrap();
+
+        socket.write_all(&pcap_bytes()).unwrap();
+      }
+    });
+
+    let mut lens = Vec::new();
+
+    loop {
+      match stream.read() {
+        Ok(rec) => lens.push(rec.get_len()),
+        Err(Error::EOF) => break,
+        Err(e) => panic!("{:?}", e),
+      }
+    }
+
+    sender.join().unwrap();
+
+    let once = vec![90, 153, 90, 90, 153, 181];
+
+    assert_eq!(lens, [once.clone(), once].concat());
+    assert_eq!(stream.get_reconnects(), 1);
+  }
+
+  #[test]
+  fn connect_refused() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    drop(listener);
+
+    assert!(matches!(
+      PcapStream::connect(addr.to_string(), PcapStreamOptions::default()),
+      Err(Error::IoError(_))
+    ));
+  }
+
+  #[test]
+  fn failed_open_joins_copier() {
+    let _guard = utils::init_for_tests();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let sender = std::thread::spawn(move || {
+      let mut socket = TcpStream::connect(addr).unwrap();
+
+      socket.write_all(b"not a capture file").unwrap();
+    });
+
+    let (socket, _) = listener.accept().unwrap();
+
+    sender.join().unwrap();
+
+    assert!(Connection::new(socket).is_err());
+  }
+}
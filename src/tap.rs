@@ -0,0 +1,587 @@
+use std::ffi::CString;
+use std::os::raw::c_void;
+
+use crate::utils;
+use crate::*;
+
+// What every tap gets besides its own data.
+#[derive(Debug)]
+pub struct TapPacket {
+  pub frame_num: u32,
+  pub abs_ts: NsTime,
+  pub rel_ts: NsTime,
+  pub len: u32,
+  pub(crate) pinfo: *mut raw::packet_info,
+}
+
+// The data a tap queues for every packet, converted from what the dissector
+// passes to tap_queue_packet().
+pub trait TapData: Sized {
+  const TAP_NAME: &'static str;
+
+  // TL_REQUIRES_* flags.
+  const FLAGS: u32 = 0;
+
+  // `edt` is the packet being tapped, `data` the dissector's tap struct.
+  unsafe fn from_tap(edt: *mut raw::epan_dissect_t, data: *const c_void) -> Option<Self>;
+}
+
+// Gets the tap_flags_t of the listener's tap_packet_cb besides the packet.
+type PacketCallback =
+  Box<dyn FnMut(&TapPacket, *mut raw::epan_dissect_t, *const c_void, raw::tap_flags_t)>;
+
+struct TapCallbacks {
+  reset: Option<Box<dyn FnMut()>>,
+  packet: PacketCallback,
+  draw: Option<Box<dyn FnMut()>>,
+}
+
+// Taps see every record dissected through a ParsedRec, in any session,
+// while the listener is alive.
+pub struct TapListener {
+  callbacks: Box<TapCallbacks>,
+}
+
+impl Drop for TapListener {
+  fn drop(&mut self) {
+    unsafe {
+      raw::remove_tap_listener(self.callbacks.as_mut() as *mut TapCallbacks as *mut c_void);
+    }
+  }
+}
+
+impl TapListener {
+  // `filter` is a display filter packets must match to be tapped.
+  pub fn register<T: TapData + 'static>(
+    filter: Option<String>,
+    mut packet: impl FnMut(&TapPacket, &T) + 'static,
+  ) -> Result<TapListener, Error> {
+    TapListener::register_raw(
+      T::TAP_NAME,
+      filter,
+      T::FLAGS,
+      Box::new(move |pkt, edt, data, _flags| {
+        if let Some(tap_data) = unsafe { T::from_tap(edt, data) } {
+          packet(pkt, &tap_data);
+        }
+      }),
+    )
+  }
+
+  pub(crate) fn register_raw(
+    tap_name: &str,
+    filter: Option<String>,
+    flags: u32,
+    packet: PacketCallback,
+  ) -> Result<TapListener, Error> {
+    let mut callbacks = Box::new(TapCallbacks {
+      reset: None,
+      packet,
+      draw: None,
+    });
+
+    let ctap_name = CString::new(tap_name).unwrap();
+    let cfilter = filter
+      .map(|f| CString::new(f).map_err(|e| Error::InvalidFilter(e.to_string())))
+      .transpose()?;
+
+    unsafe {
+      let err = raw::register_tap_listener(
+        ctap_name.as_ptr(),
+        callbacks.as_mut() as *mut TapCallbacks as *mut c_void,
+        cfilter.as_ref().map_or(std::ptr::null(), |f| f.as_ptr()),
+        flags,
+        Some(tap_reset),
+        Some(tap_packet),
+        Some(tap_draw),
+        None,
+      );
+
+      if !err.is_null() {
+        let msg = utils::cstr_to_string((*err).str_);
+        raw::g_string_free(err, true);
+
+        return Err(Error::Tap(msg));
+      }
+    }
+
+    Ok(TapListener { callbacks })
+  }
+
+  // Called by reset_all(), before a new pass over the packets.
+  pub fn on_reset(&mut self, reset: impl FnMut() + 'static) {
+    self.callbacks.reset = Some(Box::new(reset));
+  }
+
+  // Called by draw_all(), typically once all packets are dissected.
+  pub fn on_draw(&mut self, draw: impl FnMut() + 'static) {
+    self.callbacks.draw = Some(Box::new(draw));
+  }
+
+  pub fn reset_all() {
+    unsafe {
+      raw::reset_tap_listeners();
+    }
+  }
+
+  pub fn draw_all() {
+    unsafe {
+      raw::draw_tap_listeners(true);
+    }
+  }
+}
+
+unsafe extern "C" fn tap_reset(tapdata: *mut c_void) {
+  let callbacks = unsafe { &mut *(tapdata as *mut TapCallbacks) };
+
+  if let Some(ref mut reset) = callbacks.reset {
+    reset();
+  }
+}
+
+unsafe extern "C" fn tap_draw(tapdata: *mut c_void) {
+  let callbacks = unsafe { &mut *(tapdata as *mut TapCallbacks) };
+
+  if let Some(ref mut draw) = callbacks.draw {
+    draw();
+  }
+}
+
+unsafe extern "C" fn tap_packet(
+  tapdata: *mut c_void,
+  pinfo: *mut raw::packet_info,
+  edt: *mut raw::epan_dissect_t,
+  data: *const c_void,
+  flags: raw::tap_flags_t,
+) -> raw::tap_packet_status {
+  unsafe {
+    let callbacks = &mut *(tapdata as *mut TapCallbacks);
+
+    let pkt = TapPacket {
+      frame_num: (*pinfo).num,
+      abs_ts: (*pinfo).abs_ts.into(),
+      rel_ts: (*pinfo).rel_ts.into(),
+      len: (*(*pinfo).fd).pkt_len,
+      pinfo,
+    };
+
+    (callbacks.packet)(&pkt, edt, data, flags);
+  }
+
+  raw::tap_packet_status_TAP_PACKET_DONT_REDRAW
+}
+
+#[derive(Debug, Clone)]
+pub struct EthTap {
+  pub src: String,
+  pub dst: String,
+  pub ether_type: u16,
+}
+
+impl TapData for EthTap {
+  const TAP_NAME: &'static str = "eth";
+
+  unsafe fn from_tap(_edt: *mut raw::epan_dissect_t, data: *const c_void) -> Option<Self> {
+    unsafe {
+      let eth = (data as *const raw::eth_hdr).as_ref()?;
+
+      Some(EthTap {
+        src: utils::address_to_string(&eth.src),
+        dst: utils::address_to_string(&eth.dst),
+        ether_type: eth.type_,
+      })
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct IpTap {
+  pub version: u8,
+  pub tos: u8,
+  pub len: u32,
+  pub id: u16,
+  pub ttl: u8,
+  pub proto: u8,
+  pub src: String,
+  pub dst: String,
+  pub stream: u32,
+}
+
+impl TapData for IpTap {
+  const TAP_NAME: &'static str = "ip";
+
+  unsafe fn from_tap(_edt: *mut raw::epan_dissect_t, data: *const c_void) -> Option<Self> {
+    unsafe {
+      let ip = (data as *const raw::ws_ip4).as_ref()?;
+
+      Some(IpTap {
+        version: ip.ip_ver,
+        tos: ip.ip_tos,
+        len: ip.ip_len,
+        id: ip.ip_id,
+        ttl: ip.ip_ttl,
+        proto: ip.ip_proto,
+        src: utils::address_to_string(&ip.ip_src),
+        dst: utils::address_to_string(&ip.ip_dst),
+        stream: ip.ip_stream,
+      })
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct TcpTap {
+  pub src: String,
+  pub dst: String,
+  pub srcport: u16,
+  pub dstport: u16,
+  // Relative unless tcp.relative_sequence_numbers is off.
+  pub seq: u32,
+  pub ack: u32,
+  pub win: u32,
+  pub flags: u16,
+  pub seglen: Option<u32>,
+  pub stream: u32,
+}
+
+impl TapData for TcpTap {
+  const TAP_NAME: &'static str = "tcp";
+
+  unsafe fn from_tap(_edt: *mut raw::epan_dissect_t, data: *const c_void) -> Option<Self> {
+    unsafe {
+      let tcp = (data as *const raw::tcpheader).as_ref()?;
+
+      Some(TcpTap {
+        src: utils::address_to_string(&tcp.ip_src),
+        dst: utils::address_to_string(&tcp.ip_dst),
+        srcport: tcp.th_sport,
+        dstport: tcp.th_dport,
+        seq: tcp.th_seq,
+        ack: tcp.th_ack,
+        win: tcp.th_win,
+        flags: tcp.th_flags,
+        seglen: tcp.th_have_seglen.then_some(tcp.th_seglen),
+        stream: tcp.th_stream,
+      })
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct UdpTap {
+  pub src: String,
+  pub dst: String,
+  pub srcport: u16,
+  pub dstport: u16,
+  pub len: u32,
+  pub stream: u32,
+}
+
+impl TapData for UdpTap {
+  const TAP_NAME: &'static str = "udp";
+
+  unsafe fn from_tap(_edt: *mut raw::epan_dissect_t, data: *const c_void) -> Option<Self> {
+    unsafe {
+      let udp = (data as *const raw::e_udphdr).as_ref()?;
+
+      Some(UdpTap {
+        src: utils::address_to_string(&udp.ip_src),
+        dst: utils::address_to_string(&udp.ip_dst),
+        srcport: udp.uh_sport,
+        dstport: udp.uh_dport,
+        len: udp.uh_ulen,
+        stream: udp.uh_stream,
+      })
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpTap {
+  pub request_method: Option<String>,
+  // None for requests.
+  pub response_code: Option<u32>,
+  pub host: Option<String>,
+  pub request_uri: Option<String>,
+  pub full_uri: Option<String>,
+}
+
+impl TapData for HttpTap {
+  const TAP_NAME: &'static str = "http";
+
+  unsafe fn from_tap(_edt: *mut raw::epan_dissect_t, data: *const c_void) -> Option<Self> {
+    unsafe {
+      let http = (data as *const raw::http_info_value_t).as_ref()?;
+
+      Some(HttpTap {
+        request_method: utils::opt_cstr_to_string(http.request_method),
+        response_code: (http.response_code != 0).then_some(http.response_code),
+        host: utils::opt_cstr_to_string(http.http_host),
+        request_uri: utils::opt_cstr_to_string(http.request_uri),
+        full_uri: utils::opt_cstr_to_string(http.full_uri),
+      })
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct DnsTap {
+  pub id: u32,
+  pub response: bool,
+  pub opcode: u32,
+  pub rcode: Option<u32>,
+  pub queries: Vec<String>,
+  pub answers: u32,
+}
+
+// The DNS dissector's tap struct is private to it, so this one is read from
+// the DNS subtree. A frame carrying several DNS messages, as DNS over TCP
+// can, gets the fields of the first one for each of them.
+impl TapData for DnsTap {
+  const TAP_NAME: &'static str = "dns";
+  const FLAGS: u32 = raw::TL_REQUIRES_PROTO_TREE;
+
+  unsafe fn from_tap(edt: *mut raw::epan_dissect_t, _data: *const c_void) -> Option<Self> {
+    unsafe {
+      let tree = find_node((*edt).tree, field_id("dns")?);
+
+      if tree.is_null() {
+        return None;
+      }
+
+      Some(DnsTap {
+        id: tree_uint(tree, "dns.id")?,
+        response: tree_uint(tree, "dns.flags.response").unwrap_or(0) != 0,
+        opcode: tree_uint(tree, "dns.flags.opcode").unwrap_or(0),
+        rcode: tree_uint(tree, "dns.flags.rcode"),
+        queries: tree_strings(tree, "dns.qry.name"),
+        answers: tree_uint(tree, "dns.count.answers").unwrap_or(0),
+      })
+    }
+  }
+}
+
+unsafe fn field_id(name: &str) -> Option<i32> {
+  let cname = CString::new(name).ok()?;
+
+  let id = unsafe { raw::proto_registrar_get_id_byname(cname.as_ptr()) };
+
+  if id < 0 { None } else { Some(id) }
+}
+
+// The first node of field `id` under `tree`, depth first.
+unsafe fn find_node(tree: *mut raw::proto_tree, id: i32) -> *mut raw::proto_node {
+  unsafe {
+    let mut node = (*tree).first_child;
+
+    while !node.is_null() {
+      let hfinfo = (*node).hfinfo;
+
+      if !hfinfo.is_null() && (*hfinfo).id == id {
+        return node;
+      }
+
+      let found = find_node(node, id);
+
+      if !found.is_null() {
+        return found;
+      }
+
+      node = (*node).next;
+    }
+  }
+
+  std::ptr::null_mut()
+}
+
+unsafe fn tree_uint(tree: *mut raw::proto_tree, name: &str) -> Option<u32> {
+  unsafe {
+    let finfo = raw::proto_find_first_finfo(tree, field_id(name)?);
+
+    if finfo.is_null() || (*finfo).value.is_null() {
+      return None;
+    }
+
+    match (*(*(*finfo).value).ftype).ftype {
+      raw::ftenum_FT_BOOLEAN => Some(raw::fvalue_get_uinteger64((*finfo).value) as u32),
+      _ => Some(raw::fvalue_get_uinteger((*finfo).value)),
+    }
+  }
+}
+
+unsafe fn tree_strings(tree: *mut raw::proto_tree, name: &str) -> Vec<String> {
+  let mut strings = Vec::new();
+
+  unsafe {
+    let Some(id) = field_id(name) else {
+      return strings;
+    };
+
+    let finfos = raw::proto_find_finfo(tree, id);
+
+    if finfos.is_null() {
+      return strings;
+    }
+
+    for i in 0..(*finfos).len as usize {
+      let finfo = *(*finfos).pdata.add(i) as *mut raw::field_info;
+
+      if !(*finfo).value.is_null() {
+        if let Some(s) = utils::opt_cstr_to_string(raw::fvalue_get_string((*finfo).value)) {
+          strings.push(s);
+        }
+      }
+    }
+
+    raw::g_ptr_array_free(finfos, true);
+  }
+
+  strings
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+
+  // Frame numbers and tap data of every packet of `name` the tap saw.
+  fn tapped<T: TapData + Clone + 'static>(name: &str, filter: Option<&str>) -> Vec<(u32, T)> {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let packet_seen = seen.clone();
+
+    let listener = TapListener::register(filter.map(str::to_string), move |pkt, data: &T| {
+      packet_seen.borrow_mut().push((pkt.frame_num, data.clone()));
+    })
+    .unwrap();
+
+    let session = Session::new();
+    let ctx = session.new_capture_context().unwrap();
+    let path = format!("{}/data/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let mut wtap = Wtap::new(path).unwrap();
+
+    while let Ok(mut rec) = wtap.read() {
+      ctx.new_prec(&mut rec).dissect();
+    }
+
+    drop(listener);
+
+    seen.take()
+  }
+
+  #[test]
+  fn eth_tap() {
+    let _guard = utils::init_for_tests();
+
+    let packets = tapped::<EthTap>("1.pcap", None);
+
+    assert_eq!(packets.len(), 6);
+    assert_eq!(packets[0].1.src, "00:00:00:00:00:00");
+    assert_eq!(packets[0].1.ether_type, 0x0800);
+  }
+
+  #[test]
+  fn ip_tap() {
+    let _guard = utils::init_for_tests();
+
+    let packets = tapped::<IpTap>("1.pcap", Some("dns.flags.response == 1"));
+    let frames: Vec<u32> = packets.iter().map(|(num, _)| *num).collect();
+
+    assert_eq!(frames, vec![2, 5, 6]);
+
+    let ip = &packets[0].1;
+
+    assert_eq!(ip.version, 4);
+    assert_eq!(ip.proto, 17);
+    assert_eq!(ip.src, "127.0.0.53");
+    assert_eq!(ip.dst, "127.0.0.1");
+    assert_eq!(ip.len, 139);
+  }
+
+  #[test]
+  fn udp_tap() {
+    let _guard = utils::init_for_tests();
+
+    let packets = tapped::<UdpTap>("1.pcap", None);
+    let udp = &packets[0].1;
+
+    assert_eq!(packets.len(), 6);
+    assert_eq!(udp.srcport, 58125);
+    assert_eq!(udp.dstport, 53);
+    assert_eq!(udp.len, 56);
+    // Frames 3 to 6 are a second exchange from another port.
+    assert_eq!(packets[1].1.stream, udp.stream);
+    assert_ne!(packets[2].1.stream, udp.stream);
+  }
+
+  #[test]
+  fn dns_tap() {
+    let _guard = utils::init_for_tests();
+
+    let packets = tapped::<DnsTap>("1.pcap", None);
+    let summary: Vec<(u32, bool, u32)> = packets
+      .iter()
+      .map(|(_, dns)| (dns.id, dns.response, dns.answers))
+      .collect();
+
+    assert_eq!(
+      summary,
+      vec![
+        (23094, false, 0),
+        (23094, true, 2),
+        (56276, false, 0),
+        (55005, false, 0),
+        (56276, true, 2),
+        (55005, true, 2),
+      ]
+    );
+    assert_eq!(packets[1].1.rcode, Some(0));
+    assert_eq!(packets[1].1.queries, vec!["users.rust-lang.org"]);
+  }
+
+  // data/http.pcap is a TCP handshake followed by a GET and its response.
+  #[test]
+  fn tcp_tap() {
+    let _guard = utils::init_for_tests();
+
+    let packets = tapped::<TcpTap>("http.pcap", None);
+
+    assert_eq!(packets.len(), 5);
+
+    let syn = &packets[0].1;
+
+    assert_eq!((syn.srcport, syn.dstport), (50080, 80));
+    assert_eq!(syn.flags, 0x02);
+    assert_eq!(syn.seq, 0);
+
+    let request = &packets[3].1;
+
+    assert_eq!(request.src, "192.168.0.1");
+    assert_eq!(request.flags, 0x18);
+    assert_eq!((request.seq, request.ack), (1, 1));
+    assert_eq!(request.seglen, Some(65));
+    assert_eq!(request.stream, syn.stream);
+  }
+
+  #[test]
+  fn http_tap() {
+    let _guard = utils::init_for_tests();
+
+    let packets = tapped::<HttpTap>("http.pcap", None);
+
+    assert_eq!(packets.len(), 2);
+
+    let (num, request) = &packets[0];
+
+    assert_eq!(*num, 4);
+    assert_eq!(request.request_method.as_deref(), Some("GET"));
+    assert_eq!(request.request_uri.as_deref(), Some("/index.html"));
+    assert_eq!(request.host.as_deref(), Some("example.com"));
+    assert_eq!(request.response_code, None);
+
+    let (num, response) = &packets[1];
+
+    assert_eq!(*num, 5);
+    assert_eq!(response.response_code, Some(200));
+  }
+}
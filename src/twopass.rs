@@ -0,0 +1,130 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::provider::ProviderData;
+use crate::*;
+
+// Dissects a whole file once to build up dissector state, as `tshark -2`
+// does, then hands out fully dissected records in a second pass, with
+// forward references such as dns.response_in resolved.
+pub struct TwoPassDissector {
+  session: Rc<RefCell<InnerEpanSession>>,
+  ctx: CaptureContext,
+  wtap: Wtap,
+  next_frame: u32,
+}
+
+impl TwoPassDissector {
+  pub fn new(session: &Session, path: String) -> Result<TwoPassDissector, Error> {
+    let wtap = Wtap::new_random(path).map_err(Error::WsError)?;
+
+    let mut dissector = TwoPassDissector {
+      session: session.epan.clone(),
+      ctx: session.new_capture_context()?,
+      wtap,
+      next_frame: 1,
+    };
+
+    dissector.first_pass()?;
+
+    Ok(dissector)
+  }
+
+  fn first_pass(&mut self) -> Result<(), Error> {
+    let mut edt = Box::new(Edt::new());
+
+    unsafe {
+      raw::epan_dissect_init(
+        (&mut edt.edt) as *mut raw::epan_dissect_t,
+        self.session.borrow_mut().epan,
+        false,
+        false,
+      );
+    }
+
+    self.session.borrow_mut().secrets.load();
+
+    loop {
+      let rec = match self.wtap.read() {
+        Ok(rec) => rec,
+        Err(Error::EOF) => break,
+        Err(e) => return Err(e),
+      };
+
+      let raw_rec = &mut rec.rec.borrow_mut().rec;
+      let ctx = &self.ctx.inner;
+
+      unsafe {
+        let fdata = ctx
+          .borrow_mut()
+          .new_frame(raw_rec as *mut raw::wtap_rec, rec.offset);
+
+        let provider = self.session.borrow().provider.as_ref() as *const ProviderData;
+
+        (*provider).with_context(ctx, || {
+          raw::epan_dissect_run(
+            (&mut edt.edt) as *mut raw::epan_dissect_t,
+            rec.file_type,
+            raw_rec as *mut raw::wtap_rec,
+            fdata,
+            std::ptr::null_mut(),
+          )
+        });
+
+        ctx.borrow_mut().finish_frame(fdata);
+
+        raw::epan_dissect_reset((&mut edt.edt) as *mut raw::epan_dissect_t);
+      }
+    }
+
+    unsafe {
+      raw::postseq_cleanup_all_protocols();
+    }
+
+    Ok(())
+  }
+
+  pub fn get_frame_count(&self) -> u32 {
+    self.ctx.get_frame_count()
+  }
+
+  pub fn get(&mut self, frame_num: u32) -> Result<ParsedRec, Error> {
+    self.get_primed(frame_num, None)
+  }
+
+  pub fn read(&mut self) -> Result<ParsedRec, Error> {
+    let prec = self.get_primed(self.next_frame, None)?;
+
+    self.next_frame += 1;
+
+    Ok(prec)
+  }
+
+  pub fn read_with_dfilter(&mut self, dfilter: &DFilter) -> Result<ParsedRec, Error> {
+    let prec = self.get_primed(self.next_frame, Some(dfilter))?;
+
+    self.next_frame += 1;
+
+    Ok(prec)
+  }
+
+  fn get_primed(&mut self, frame_num: u32, dfilter: Option<&DFilter>) -> Result<ParsedRec, Error> {
+    let offset = match self.ctx.inner.borrow_mut().get_frame(frame_num) {
+      Some(fdata) => unsafe { (*fdata).file_off },
+      None => return Err(Error::EOF),
+    };
+
+    let mut rec = self.wtap.seek_read(offset).map_err(Error::WsError)?;
+
+    let mut prec = self.ctx.new_prec(&mut rec);
+
+    prec.frame_num = frame_num;
+
+    if let Some(dfilter) = dfilter {
+      prec.prime_with_dfilter(dfilter);
+    }
+
+    prec.dissect();
+
+    Ok(prec)
+  }
+}
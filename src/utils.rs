@@ -1,8 +1,118 @@
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+use crate::Error;
 use crate::raw;
 
-
 pub(crate) fn cstr_to_string(cstr: *const raw::gchar) -> String {
   unsafe { CStr::from_ptr(cstr).to_str().unwrap().to_string() }
-}
\ No newline at end of file
+}
+
+pub(crate) fn opt_cstr_to_string(cstr: *const raw::gchar) -> Option<String> {
+  if cstr.is_null() {
+    None
+  } else {
+    Some(cstr_to_string(cstr))
+  }
+}
+
+pub(crate) fn address_to_string(addr: &raw::address) -> String {
+  unsafe {
+    let s = raw::address_to_str(std::ptr::null_mut(), addr as *const raw::address);
+    let ret = opt_cstr_to_string(s).unwrap_or_default();
+
+    raw::wmem_free(std::ptr::null_mut(), s as *mut std::os::raw::c_void);
+
+    ret
+  }
+}
+
+static FIFO_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn make_fifo(name: &str) -> Result<PathBuf, Error> {
+  let fifo = std::env::temp_dir().join(format!(
+    "rust-wireshark-{}-{}-{}",
+    name,
+    std::process::id(),
+    FIFO_COUNTER.fetch_add(1, Ordering::Relaxed)
+  ));
+
+  let cfifo = CString::new(fifo.to_string_lossy().into_owned()).unwrap();
+
+  if unsafe { raw::mkfifo(cfifo.as_ptr(), 0o600) } != 0 {
+    return Err(Error::IoError(std::io::Error::last_os_error()));
+  }
+
+  Ok(fifo)
+}
+
+// Formats a record the way UAT files and `-o uat:<table>:` expect it.
+pub(crate) fn uat_record(fields: &[&str]) -> String {
+  fields
+    .iter()
+    .map(|field| {
+      let escaped: String = field
+        .chars()
+        .map(|c| match c {
+          '"' => "\\x22".to_string(),
+          '\\' => "\\x5c".to_string(),
+          c => c.to_string(),
+        })
+        .collect();
+
+      format!("\"{}\"", escaped)
+    })
+    .collect::<Vec<String>>()
+    .join(",")
+}
+
+// Runs `f` with a FILE* for the print.h writers and copies what it wrote
+// into `w`.
+pub(crate) fn with_memstream<W: Write>(
+  w: &mut W,
+  f: impl FnOnce(*mut raw::FILE),
+) -> Result<(), Error> {
+  let mut buf: *mut std::os::raw::c_char = std::ptr::null_mut();
+  let mut size: usize = 0;
+
+  unsafe {
+    let fh = raw::open_memstream(&mut buf, &mut size);
+
+    if fh.is_null() {
+      return Err(Error::IoError(std::io::Error::last_os_error()));
+    }
+
+    f(fh);
+
+    raw::fclose(fh);
+
+    let ret = if buf.is_null() {
+      Ok(())
+    } else {
+      w.write_all(std::slice::from_raw_parts(buf as *const u8, size))
+    };
+
+    raw::free(buf as *mut std::os::raw::c_void);
+
+    ret.map_err(Error::IoError)
+  }
+}
+
+// libwireshark is initialized once per process and isn't thread safe, so
+// tests that use it hold this while they run.
+#[cfg(test)]
+pub(crate) fn init_for_tests() -> std::sync::MutexGuard<'static, ()> {
+  static INIT: std::sync::Once = std::sync::Once::new();
+  static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+  let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+  INIT.call_once(|| {
+    crate::wtap_init();
+    assert!(crate::epan_init());
+  });
+
+  guard
+}
@@ -30,6 +30,7 @@ impl fmt::Debug for InnerWtapRec {
   }
 }
 
+#[derive(Debug)]
 pub struct WtapRec {
   pub(crate) rec: Rc<RefCell<InnerWtapRec>>,
   pub(crate) offset: raw::gint64,
@@ -59,7 +60,7 @@ impl InnerWtapRec {
 }
 
 impl WtapRec {
-  fn new() -> WtapRec {
+  pub(crate) fn new() -> WtapRec {
     WtapRec {
       rec: Rc::new(RefCell::new(InnerWtapRec::new())),
       offset: 0,
@@ -67,13 +68,63 @@ impl WtapRec {
     }
   }
 
-  fn set_file_type(&mut self, ftype: i32) {
+  pub(crate) fn set_file_type(&mut self, ftype: i32) {
     self.file_type = ftype;
   }
+
+  pub fn get_offset(&self) -> i64 {
+    self.offset
+  }
+
+  pub(crate) fn is_packet(&self) -> bool {
+    self.rec.borrow().rec.rec_type == raw::REC_TYPE_PACKET
+  }
+
+  pub fn get_encap(&self) -> i32 {
+    unsafe { self.rec.borrow().rec.rec_header.packet_header.pkt_encap }
+  }
+
+  pub fn get_caplen(&self) -> u32 {
+    unsafe { self.rec.borrow().rec.rec_header.packet_header.caplen }
+  }
+
+  pub fn get_len(&self) -> u32 {
+    unsafe { self.rec.borrow().rec.rec_header.packet_header.len }
+  }
+
+  pub fn get_data(&self) -> Vec<u8> {
+    self.with_data(|data| data.to_vec())
+  }
+
+  pub(crate) fn with_data<T>(&self, f: impl FnOnce(&[u8]) -> T) -> T {
+    let inner = self.rec.borrow();
+    let buf = &inner.rec.data;
+
+    if buf.data.is_null() {
+      return f(&[]);
+    }
+
+    unsafe {
+      f(std::slice::from_raw_parts(
+        buf.data.add(buf.start),
+        buf.first_free - buf.start,
+      ))
+    }
+  }
 }
 
 impl Wtap {
   pub fn new(path: String) -> Result<Wtap, WsError> {
+    Wtap::open(path, false)
+  }
+
+  // Also opens the random-access stream that seek_read() needs. Fails on
+  // pipes.
+  pub fn new_random(path: String) -> Result<Wtap, WsError> {
+    Wtap::open(path, true)
+  }
+
+  fn open(path: String, do_random: bool) -> Result<Wtap, WsError> {
     let mut err = WsError::new();
 
     let cstr = CString::new(path.clone()).unwrap();
@@ -84,13 +135,17 @@ impl Wtap {
         raw::WTAP_TYPE_AUTO,
         (&mut err.err) as *mut ::std::os::raw::c_int,
         (&mut err.errInfo) as *mut *mut raw::gchar,
-        false,
+        do_random,
       );
 
       if wth.is_null() {
         Err(err)
       } else {
         let file_type = raw::wtap_file_type_subtype(wth);
+
+        // Hand Decryption Secrets Blocks over to the dissectors.
+        raw::wtap_set_cb_new_secrets(wth, Some(raw::secrets_wtap_callback));
+
         Ok(Wtap {
           wth,
           path,
@@ -141,10 +196,10 @@ impl Wtap {
       );
 
       if ret {
-        Err(err)
-      } else {
         rec.offset = offset;
         Ok(rec)
+      } else {
+        Err(err)
       }
     }
   }
@@ -4,12 +4,35 @@
 
 #include <wireshark.h>
 #include <epan/column-info.h>
+#include <epan/column.h>
+#include <epan/column-utils.h>
 #include <epan/epan.h>
 #include <epan/epan_dissect.h>
 #include <epan/print.h>
+#include <wsutil/json_dumper.h>
+#include <epan/prefs.h>
+#include <epan/prefs-int.h>
+#include <epan/packet.h>
+#include <epan/decode_as.h>
+#include <epan/secrets.h>
+#include <epan/tap.h>
+#include <epan/color_filters.h>
+#include <epan/conversation_table.h>
+#include <epan/address_types.h>
+#include <epan/dissectors/packet-eth.h>
+#include <epan/dissectors/packet-ip.h>
+#include <epan/dissectors/packet-tcp.h>
+#include <epan/dissectors/packet-udp.h>
+#include <epan/dissectors/packet-http.h>
 #include <epan/ftypes/ftypes.h>
 #include <epan/ftypes/ftypes-int.h>
 #include <epan/dfilter/dfilter.h>
 #include <wiretap/wtap.h>
+#include <wsutil/filesystem.h>
+#include <wiretap/pcap-encap.h>
+#include <pcap/pcap.h>
+#include <netinet/in.h>
+#include <sys/stat.h>
+#include <fcntl.h>
 
 #endif
\ No newline at end of file